@@ -0,0 +1,128 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use zip::ZipArchive;
+
+use crate::{
+    picture::Source,
+    playlist::{self, SortOrder},
+    Result, RvuError,
+};
+
+/// Extensions of the ZIP archives whose images are viewed one after the other, such as comic
+/// books.
+const EXTENSIONS: [&str; 2] = ["zip", "cbz"];
+
+/// What macOS puts the resource forks of the files it archives in, which aren't images even
+/// when they're named like one.
+const MACOS_METADATA: &str = "__MACOSX/";
+
+/// The most an image in an archive may unpack to, far more than any page of a comic book.
+const MAX_ENTRY_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Whether `path` is named like an archive of images.
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            EXTENSIONS
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Lists the images in the archive at `path`, in the natural order of their names inside it.
+/// Entries that aren't named like a supported image are skipped. None of them is read until
+/// it's loaded.
+pub fn images(path: &Path) -> Result<Vec<Source>> {
+    let archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut names: Vec<PathBuf> = archive
+        .file_names()
+        .filter(|name| !name.starts_with(MACOS_METADATA))
+        .map(PathBuf::from)
+        .filter(|name| playlist::is_supported(name))
+        .collect();
+    SortOrder::Natural.sort(&mut names);
+    Ok(names
+        .into_iter()
+        .map(|name| Source::Archived {
+            archive: path.to_path_buf(),
+            entry: name.to_string_lossy().into_owned(),
+        })
+        .collect())
+}
+
+/// Reads the entry named `entry` out of the archive at `path`.
+pub fn read(path: &Path, entry: &str) -> Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    read_entry(&mut archive, entry, MAX_ENTRY_SIZE)
+}
+
+/// Decompresses `entry` of `archive`, giving up once it comes to more than `limit` bytes. The
+/// size the archive claims isn't trusted, a few bytes can claim anything and unpack to gigabytes.
+fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    entry: &str,
+    limit: u64,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    archive
+        .by_name(entry)?
+        .take(limit + 1)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > limit {
+        return Err(RvuError::EntryTooLarge(limit));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    use super::*;
+
+    fn zipped(entries: &[(&str, &[u8])]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, bytes) in entries {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(bytes).unwrap();
+        }
+        ZipArchive::new(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn entries_are_read_whole() {
+        let mut archive = zipped(&[("a.png", b"first"), ("b.png", b"second")]);
+        assert_eq!(read_entry(&mut archive, "b.png", 100).unwrap(), b"second");
+        assert_eq!(read_entry(&mut archive, "a.png", 5).unwrap(), b"first");
+    }
+
+    #[test]
+    fn entries_past_the_limit_are_refused() {
+        // Squeezed into about a kilobyte, however much it unpacks to.
+        let zeros = vec![0; 1 << 20];
+        let mut archive = zipped(&[("bomb.png", &zeros)]);
+        assert!(archive.by_name("bomb.png").unwrap().compressed_size() < 1 << 12);
+        assert!(matches!(
+            read_entry(&mut archive, "bomb.png", 1 << 16),
+            Err(RvuError::EntryTooLarge(limit)) if limit == 1 << 16
+        ));
+        assert!(read_entry(&mut archive, "bomb.png", 1 << 20).is_ok());
+    }
+
+    #[test]
+    fn missing_entries_are_errors() {
+        let mut archive = zipped(&[("a.png", b"first")]);
+        assert!(matches!(
+            read_entry(&mut archive, "b.png", 100),
+            Err(RvuError::ArchiveError(_))
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+use image::RgbaImage;
+
+use crate::{
+    picture::Source,
+    render::{prepare, Backdrop, Transform},
+};
+
+/// How close to the divider, in window pixels, a press of the mouse grabs it.
+const GRAB_DISTANCE: f64 = 6.0;
+
+/// How the two images of `--compare` share the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// The first image left of the divider, the second right of it.
+    Split,
+    /// Only the first image, to flip back and forth with the second one.
+    First,
+    /// Only the second image.
+    Second,
+}
+
+impl Layout {
+    pub fn next(self) -> Self {
+        match self {
+            Layout::Split => Layout::First,
+            Layout::First => Layout::Second,
+            Layout::Second => Layout::Split,
+        }
+    }
+}
+
+/// The second image of `--compare`, displayed with the same view and transform as the first one
+/// so that the same pixels of both line up.
+#[derive(Debug)]
+pub struct Comparison {
+    pub source: Source,
+    frame: RgbaImage,
+    /// The frame with `transform` applied, ready to draw.
+    pub image: RgbaImage,
+    transform: Transform,
+    pub layout: Layout,
+    /// Where the divider between the two images is, as a share of the window width.
+    divider: f64,
+}
+
+impl Comparison {
+    pub fn new(source: Source, frame: RgbaImage, transform: Transform, backdrop: Backdrop) -> Self {
+        Self {
+            source,
+            image: prepare(&frame, transform, backdrop),
+            frame,
+            transform,
+            layout: Layout::Split,
+            divider: 0.5,
+        }
+    }
+
+    /// Catches up with `transform` having been changed for the first image.
+    pub fn follow(&mut self, transform: Transform, backdrop: Backdrop) {
+        if transform != self.transform {
+            self.transform = transform;
+            self.image = prepare(&self.frame, transform, backdrop);
+        }
+    }
+
+    /// The column of a `width` pixels wide window the second image starts at when split.
+    pub fn divider_x(&self, width: u32) -> u32 {
+        (self.divider * f64::from(width)).round() as u32
+    }
+
+    /// Whether pressing the mouse at `x` in a `width` pixels wide window grabs the divider.
+    pub fn grabs(&self, x: f64, width: u32) -> bool {
+        self.layout == Layout::Split
+            && (x - f64::from(self.divider_x(width))).abs() <= GRAB_DISTANCE
+    }
+
+    /// Moves the divider to `x` in a `width` pixels wide window.
+    pub fn drag_to(&mut self, x: f64, width: u32) {
+        self.divider = (x / f64::from(width.max(1))).clamp(0.0, 1.0);
+    }
+}
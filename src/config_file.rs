@@ -0,0 +1,63 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{keys::KeyNames, render::FitMode, Color, Result, RvuError};
+
+/// Defaults read from `config.toml` in the `img-vwr` config directory, e.g.
+/// `~/.config/img-vwr/config.toml`. Everything is optional and the command line wins.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub fit: Option<FitMode>,
+    pub background: Option<Color>,
+    /// Whether transparent areas are shown over a checkerboard.
+    pub checker: Option<bool>,
+    /// Whether the window opens in the middle of the screen.
+    pub center: Option<bool>,
+    /// Seconds between slides.
+    slideshow: Option<f32>,
+    /// The keys to bind actions to instead of the default ones, such as `quit = ["q", "ctrl+w"]`,
+    /// or `"none"` to leave an action unbound. A character that isn't the name of a key, such as
+    /// `"#"`, is bound to whichever key types it.
+    #[serde(default)]
+    pub keys: BTreeMap<String, KeyNames>,
+}
+
+impl FileConfig {
+    /// Where the config file lives, if the platform has a config directory at all.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("img-vwr").join("config.toml"))
+    }
+
+    /// Reads the config file. Not having one is the same as having an empty one.
+    pub fn load() -> Result<Self> {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let invalid = |message: String| RvuError::ConfigError {
+            path: path.clone(),
+            message,
+        };
+        let config: Self = toml::from_str(&text).map_err(|err| invalid(err.to_string()))?;
+        if let Some(seconds) = config.slideshow {
+            if !(seconds > 0.0 && seconds.is_finite()) {
+                return Err(invalid(
+                    "`slideshow` must be a positive number of seconds".to_string(),
+                ));
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn slideshow(&self) -> Option<Duration> {
+        self.slideshow.map(Duration::from_secs_f32)
+    }
+}
@@ -0,0 +1,217 @@
+/// Where the tag table of an ICC profile starts, right after the fixed size header.
+const TAG_TABLE: usize = 128;
+const TAG_ENTRY_SIZE: usize = 12;
+/// Where the records of a `multiLocalizedUnicodeType` tag start, and how big one is at least.
+const MLUC_RECORDS: usize = 16;
+const MLUC_RECORD_SIZE: usize = 12;
+
+/// The name of the ICC `profile`, as its description tag gives it, such as `sRGB IEC61966-2.1`
+/// or `Display P3`. `None` if the profile has no readable description.
+pub fn profile_name(profile: &[u8]) -> Option<String> {
+    let description = tag(profile, b"desc")?;
+    let name = match description.get(..4)? {
+        b"desc" => ascii_description(description)?,
+        b"mluc" => localized_description(description)?,
+        _ => return None,
+    };
+    let name = name.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Whether the profile called `name` is one of the variants of sRGB, which is what images
+/// without a profile are shown as anyway.
+pub fn is_srgb(name: &str) -> bool {
+    name.to_ascii_lowercase().contains("srgb")
+}
+
+/// The data of the tag with signature `signature`.
+fn tag<'a>(profile: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+    // No more than fit in the profile, whatever it claims.
+    let room = profile.len().saturating_sub(TAG_TABLE + 4) / TAG_ENTRY_SIZE;
+    let count = (read_u32(profile, TAG_TABLE)? as usize).min(room);
+    (0..count).find_map(|i| {
+        let entry = TAG_TABLE + 4 + i * TAG_ENTRY_SIZE;
+        if profile.get(entry..entry + 4)? != signature {
+            return None;
+        }
+        let offset = read_u32(profile, entry + 4)? as usize;
+        let size = read_u32(profile, entry + 8)? as usize;
+        profile.get(offset..offset.checked_add(size)?)
+    })
+}
+
+/// The text of a version 2 `textDescriptionType` tag.
+fn ascii_description(data: &[u8]) -> Option<String> {
+    let length = read_u32(data, 8)? as usize;
+    let text = data.get(12..12usize.checked_add(length)?)?;
+    Some(String::from_utf8_lossy(text).into_owned())
+}
+
+/// The English text of a version 4 `multiLocalizedUnicodeType` tag, or its first one if
+/// there's no English.
+fn localized_description(data: &[u8]) -> Option<String> {
+    let record_size = read_u32(data, 12)? as usize;
+    // A record holds at least its language, country, length and offset.
+    if record_size < MLUC_RECORD_SIZE {
+        return None;
+    }
+    let room = data.len().saturating_sub(MLUC_RECORDS) / record_size;
+    let count = (read_u32(data, 8)? as usize).min(room);
+    let records = (0..count).map(|i| MLUC_RECORDS + i * record_size);
+    let record = records
+        .clone()
+        .find(|&record| data.get(record..record + 2) == Some(b"en"))
+        .or_else(|| records.clone().next())?;
+    let length = read_u32(data, record + 4)? as usize;
+    let offset = read_u32(data, record + 8)? as usize;
+    let text = data.get(offset..offset.checked_add(length)?)?;
+    let units: Vec<u16> = text
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// A profile with nothing but a `desc` tag holding `description`, which claims to have
+    /// `count` tags.
+    fn profile(count: u32, description: &[u8]) -> Vec<u8> {
+        let mut profile = vec![0; TAG_TABLE];
+        profile.extend(count.to_be_bytes());
+        let offset = TAG_TABLE + 4 + TAG_ENTRY_SIZE;
+        profile.extend(b"desc");
+        profile.extend((offset as u32).to_be_bytes());
+        profile.extend((description.len() as u32).to_be_bytes());
+        profile.extend(description);
+        profile
+    }
+
+    /// A version 2 description of `text`.
+    fn ascii(text: &str) -> Vec<u8> {
+        let mut tag = b"desc\0\0\0\0".to_vec();
+        tag.extend((text.len() as u32 + 1).to_be_bytes());
+        tag.extend(text.as_bytes());
+        tag.push(0);
+        tag
+    }
+
+    /// A version 4 description with a record for every language and text in `records`, which
+    /// claims to have `count` records.
+    fn localized(count: u32, records: &[(&str, &str)]) -> Vec<u8> {
+        let mut tag = b"mluc\0\0\0\0".to_vec();
+        tag.extend(count.to_be_bytes());
+        tag.extend((MLUC_RECORD_SIZE as u32).to_be_bytes());
+        let mut offset = MLUC_RECORDS + records.len() * MLUC_RECORD_SIZE;
+        let mut texts = Vec::new();
+        for (language, text) in records {
+            let units: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+            tag.extend(language.as_bytes());
+            tag.extend(b"US");
+            tag.extend((units.len() as u32).to_be_bytes());
+            tag.extend((offset as u32).to_be_bytes());
+            offset += units.len();
+            texts.extend(units);
+        }
+        tag.extend(texts);
+        tag
+    }
+
+    #[test]
+    fn reads_version_2_descriptions() {
+        let profile = profile(1, &ascii("sRGB IEC61966-2.1"));
+        assert_eq!(profile_name(&profile).as_deref(), Some("sRGB IEC61966-2.1"));
+    }
+
+    #[test]
+    fn reads_version_4_descriptions_in_english() {
+        let description = localized(2, &[("de", "Anzeige P3"), ("en", "Display P3")]);
+        assert_eq!(
+            profile_name(&profile(1, &description)).as_deref(),
+            Some("Display P3")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_first_language() {
+        let description = localized(2, &[("fr", "Écran P3"), ("de", "Anzeige P3")]);
+        assert_eq!(
+            profile_name(&profile(1, &description)).as_deref(),
+            Some("Écran P3")
+        );
+    }
+
+    #[test]
+    fn ignores_blank_and_unknown_descriptions() {
+        assert_eq!(profile_name(&profile(1, &ascii("  \0 "))), None);
+        assert_eq!(profile_name(&profile(1, b"text\0\0\0\0Display P3")), None);
+        assert_eq!(profile_name(&profile(0, &ascii("Display P3"))), None);
+        assert_eq!(profile_name(&[]), None);
+    }
+
+    #[test]
+    fn survives_truncated_profiles() {
+        for full in [
+            profile(1, &ascii("Display P3")),
+            profile(1, &localized(1, &[("en", "Display P3")])),
+        ] {
+            for length in 0..full.len() {
+                assert_eq!(profile_name(&full[..length]), None, "cut at {}", length);
+            }
+        }
+    }
+
+    #[test]
+    fn survives_inflated_tag_counts() {
+        let started = Instant::now();
+        let profile = profile(u32::MAX, &ascii("Display P3"));
+        assert_eq!(profile_name(&profile).as_deref(), Some("Display P3"));
+        assert_eq!(profile_name(&profile[..TAG_TABLE + 4]), None);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn survives_inflated_record_counts() {
+        let started = Instant::now();
+        let description = localized(u32::MAX, &[("de", "Anzeige P3")]);
+        assert_eq!(
+            profile_name(&profile(1, &description)).as_deref(),
+            Some("Anzeige P3")
+        );
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn rejects_bad_records_and_offsets() {
+        let mut description = localized(1, &[("en", "Display P3")]);
+        description[12..16].copy_from_slice(&0u32.to_be_bytes());
+        assert_eq!(profile_name(&profile(1, &description)), None);
+
+        let mut description = localized(1, &[("en", "Display P3")]);
+        description[24..28].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(profile_name(&profile(1, &description)), None);
+
+        let mut profile = profile(1, &ascii("Display P3"));
+        let entry = TAG_TABLE + 4;
+        profile[entry + 4..entry + 12].copy_from_slice(&[0xff; 8]);
+        assert_eq!(profile_name(&profile), None);
+    }
+
+    #[test]
+    fn recognizes_srgb_variants() {
+        assert!(is_srgb("sRGB IEC61966-2.1"));
+        assert!(is_srgb("sRGB built-in"));
+        assert!(is_srgb("c2 SRGB"));
+        assert!(!is_srgb("Display P3"));
+        assert!(!is_srgb("Adobe RGB (1998)"));
+    }
+}
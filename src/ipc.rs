@@ -0,0 +1,150 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+};
+
+#[cfg(unix)]
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    sync::mpsc,
+    thread,
+};
+
+use winit::event_loop::EventLoopProxy;
+
+/// Something a script asked for over the `--ipc` socket.
+#[derive(Debug)]
+pub enum Command {
+    Next,
+    Prev,
+    /// Set the zoom level, 1 being actual size: one image pixel per logical pixel.
+    Zoom(f32),
+    /// Replace the playlist like dropping the file or directory onto the window would.
+    Open(PathBuf),
+    Quit,
+}
+
+impl Command {
+    /// Parses one line sent to the socket, such as `zoom 1.5` or `open /tmp/cat.png`.
+    fn parse(line: &str) -> Result<Self, String> {
+        let (name, argument) = match line.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (line, ""),
+        };
+        match (name, argument) {
+            ("next", "") => Ok(Command::Next),
+            ("prev", "") => Ok(Command::Prev),
+            ("quit", "") => Ok(Command::Quit),
+            ("zoom", zoom) => match zoom.parse::<f32>() {
+                Ok(zoom) if zoom > 0.0 && zoom.is_finite() => Ok(Command::Zoom(zoom)),
+                _ => Err(format!("`{}` is not a positive zoom level", zoom)),
+            },
+            ("open", "") => Err("`open` needs a path".to_string()),
+            ("open", path) => Ok(Command::Open(PathBuf::from(path))),
+            ("next" | "prev" | "quit", _) => Err(format!("`{}` takes no argument", name)),
+            _ => Err(format!("unknown command `{}`", name)),
+        }
+    }
+}
+
+/// A command on its way to the event loop, along with where to send how it went.
+#[derive(Debug)]
+pub struct Request {
+    pub command: Command,
+    reply: Sender<Result<(), String>>,
+}
+
+impl Request {
+    /// Tells the script whether the command worked.
+    pub fn reply(self, result: Result<(), String>) {
+        // The script may have hung up without waiting for the answer.
+        let _ = self.reply.send(result);
+    }
+}
+
+/// Listens on a Unix socket at `path` for lines of commands and hands them to the event loop
+/// through `proxy`. Every line is answered with `ok` or `error: ` and what went wrong.
+#[cfg(unix)]
+pub fn listen<T>(path: &Path, proxy: EventLoopProxy<T>) -> io::Result<()>
+where
+    T: From<Request> + Send + 'static,
+{
+    let listener = match UnixListener::bind(path) {
+        Ok(listener) => listener,
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse && is_stale_socket(path) => {
+            fs::remove_file(path)?;
+            UnixListener::bind(path)?
+        }
+        Err(err) => return Err(err),
+    };
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let proxy = proxy.clone();
+            thread::spawn(move || serve(stream, proxy));
+        }
+    });
+    Ok(())
+}
+
+/// Whether `path` is a socket nobody answers on, left behind by a viewer that didn't get to
+/// clean up. Anything else that's in the way, such as a regular file, is left alone.
+#[cfg(unix)]
+fn is_stale_socket(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket())
+        && UnixStream::connect(path).is_err()
+}
+
+#[cfg(not(unix))]
+pub fn listen<T>(_path: &Path, _proxy: EventLoopProxy<T>) -> io::Result<()>
+where
+    T: From<Request> + Send + 'static,
+{
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "sockets are only supported on Unix",
+    ))
+}
+
+/// Answers the commands of one connection until it's closed.
+#[cfg(unix)]
+fn serve<T>(stream: UnixStream, proxy: EventLoopProxy<T>)
+where
+    T: From<Request> + Send + 'static,
+{
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let result = Command::parse(line).and_then(|command| {
+            let (reply, answer) = mpsc::channel();
+            proxy
+                .send_event(Request { command, reply }.into())
+                .map_err(|_| "the viewer is closing".to_string())?;
+            answer
+                .recv()
+                .unwrap_or_else(|_| Err("the viewer is closing".to_string()))
+        });
+        let answer = match result {
+            Ok(()) => "ok".to_string(),
+            Err(err) => format!("error: {}", err),
+        };
+        if writeln!(writer, "{}", answer).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,721 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use serde::Deserialize;
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+/// Something a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Next,
+    Prev,
+    /// Pan left, or go to the previous image if there's nothing more to see to the left.
+    Left,
+    /// Pan right, or go to the next image if there's nothing more to see to the right.
+    Right,
+    Up,
+    Down,
+    /// Go to the previous page or frame, or scroll up by a window.
+    PageUp,
+    /// Go to the next page or frame, or scroll down by a window.
+    PageDown,
+    Top,
+    Bottom,
+    ZoomIn,
+    ZoomOut,
+    /// Undo every change to the view and the image.
+    Reset,
+    /// Resize the window to the image.
+    FitWindow,
+    /// Switch to the next fit mode.
+    Fit,
+    /// Switch between distorting the image to fill the window and keeping its aspect ratio.
+    Stretch,
+    Fullscreen,
+    /// Pause animations and the slideshow.
+    Pause,
+    Info,
+    Histogram,
+    AlwaysOnTop,
+    RotateClockwise,
+    RotateCounterclockwise,
+    FlipHorizontal,
+    FlipVertical,
+    Grayscale,
+    Invert,
+    Brighter,
+    Darker,
+    MoreContrast,
+    LessContrast,
+    /// Crop to the selection.
+    Crop,
+    /// Show the enlarged pixels around the cursor while held down.
+    Magnify,
+    /// Print the color of the pixel under the cursor.
+    PickColor,
+    /// Switch to the next scaling filter.
+    Filter,
+    /// Switch the lines between pixels at high zooms on and off.
+    PixelGrid,
+    Copy,
+    Save,
+    /// Switch to the next format to save in.
+    SaveFormat,
+    Delete,
+    /// Open the image in an editor.
+    Edit,
+    /// Go back to the contact sheet of `--grid`.
+    Grid,
+    /// Switch between the two images of `--compare` and showing them side by side.
+    Compare,
+    /// Show which keys do what.
+    Help,
+}
+
+impl Action {
+    /// Every action, by the name the config file knows it by.
+    const NAMES: [(&'static str, Action); 45] = [
+        ("quit", Action::Quit),
+        ("next", Action::Next),
+        ("prev", Action::Prev),
+        ("left", Action::Left),
+        ("right", Action::Right),
+        ("up", Action::Up),
+        ("down", Action::Down),
+        ("page_up", Action::PageUp),
+        ("page_down", Action::PageDown),
+        ("top", Action::Top),
+        ("bottom", Action::Bottom),
+        ("zoom_in", Action::ZoomIn),
+        ("zoom_out", Action::ZoomOut),
+        ("reset", Action::Reset),
+        ("fit_window", Action::FitWindow),
+        ("fit", Action::Fit),
+        ("stretch", Action::Stretch),
+        ("fullscreen", Action::Fullscreen),
+        ("pause", Action::Pause),
+        ("info", Action::Info),
+        ("histogram", Action::Histogram),
+        ("always_on_top", Action::AlwaysOnTop),
+        ("rotate_clockwise", Action::RotateClockwise),
+        ("rotate_counterclockwise", Action::RotateCounterclockwise),
+        ("flip_horizontal", Action::FlipHorizontal),
+        ("flip_vertical", Action::FlipVertical),
+        ("grayscale", Action::Grayscale),
+        ("invert", Action::Invert),
+        ("brighter", Action::Brighter),
+        ("darker", Action::Darker),
+        ("more_contrast", Action::MoreContrast),
+        ("less_contrast", Action::LessContrast),
+        ("crop", Action::Crop),
+        ("magnify", Action::Magnify),
+        ("pick_color", Action::PickColor),
+        ("filter", Action::Filter),
+        ("pixel_grid", Action::PixelGrid),
+        ("copy", Action::Copy),
+        ("save", Action::Save),
+        ("save_format", Action::SaveFormat),
+        ("delete", Action::Delete),
+        ("edit", Action::Edit),
+        ("grid", Action::Grid),
+        ("compare", Action::Compare),
+        ("help", Action::Help),
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|&(_, action)| action)
+    }
+}
+
+/// The keys every action is bound to unless the config file says otherwise.
+const DEFAULT_KEYS: &[(&str, Action)] = &[
+    ("escape", Action::Quit),
+    ("q", Action::Quit),
+    ("ctrl+w", Action::Quit),
+    ("n", Action::Next),
+    ("p", Action::Prev),
+    ("left", Action::Left),
+    ("right", Action::Right),
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("page_up", Action::PageUp),
+    ("page_down", Action::PageDown),
+    ("home", Action::Top),
+    ("end", Action::Bottom),
+    ("+", Action::ZoomIn),
+    ("=", Action::ZoomIn),
+    ("numpad_add", Action::ZoomIn),
+    ("-", Action::ZoomOut),
+    ("numpad_subtract", Action::ZoomOut),
+    ("0", Action::Reset),
+    ("numpad0", Action::Reset),
+    ("a", Action::FitWindow),
+    ("w", Action::Fit),
+    ("shift+w", Action::Stretch),
+    ("f", Action::Fullscreen),
+    ("space", Action::Pause),
+    ("i", Action::Info),
+    ("shift+h", Action::Histogram),
+    ("t", Action::AlwaysOnTop),
+    ("r", Action::RotateClockwise),
+    ("shift+r", Action::RotateCounterclockwise),
+    ("h", Action::FlipHorizontal),
+    ("v", Action::FlipVertical),
+    ("g", Action::Grayscale),
+    ("x", Action::Invert),
+    ("]", Action::Brighter),
+    ("[", Action::Darker),
+    // `}` and `{` on most layouts.
+    ("shift+]", Action::MoreContrast),
+    ("shift+[", Action::LessContrast),
+    ("return", Action::Crop),
+    ("m", Action::Magnify),
+    (",", Action::PickColor),
+    ("l", Action::Filter),
+    ("#", Action::PixelGrid),
+    ("c", Action::Copy),
+    ("s", Action::Save),
+    ("shift+s", Action::SaveFormat),
+    ("delete", Action::Delete),
+    ("e", Action::Edit),
+    ("tab", Action::Grid),
+    ("o", Action::Compare),
+    // `?` on most layouts.
+    ("shift+/", Action::Help),
+];
+
+/// Names of keys that aren't simply the letter or digit on them.
+const KEY_NAMES: &[(&str, VirtualKeyCode)] = &[
+    ("escape", VirtualKeyCode::Escape),
+    ("esc", VirtualKeyCode::Escape),
+    ("return", VirtualKeyCode::Return),
+    ("enter", VirtualKeyCode::Return),
+    ("space", VirtualKeyCode::Space),
+    ("tab", VirtualKeyCode::Tab),
+    ("backspace", VirtualKeyCode::Back),
+    ("delete", VirtualKeyCode::Delete),
+    ("insert", VirtualKeyCode::Insert),
+    ("left", VirtualKeyCode::Left),
+    ("right", VirtualKeyCode::Right),
+    ("up", VirtualKeyCode::Up),
+    ("down", VirtualKeyCode::Down),
+    ("page_up", VirtualKeyCode::PageUp),
+    ("page_down", VirtualKeyCode::PageDown),
+    ("home", VirtualKeyCode::Home),
+    ("end", VirtualKeyCode::End),
+    ("+", VirtualKeyCode::Plus),
+    ("plus", VirtualKeyCode::Plus),
+    ("-", VirtualKeyCode::Minus),
+    ("minus", VirtualKeyCode::Minus),
+    ("=", VirtualKeyCode::Equals),
+    ("equals", VirtualKeyCode::Equals),
+    (",", VirtualKeyCode::Comma),
+    ("comma", VirtualKeyCode::Comma),
+    (".", VirtualKeyCode::Period),
+    ("period", VirtualKeyCode::Period),
+    ("/", VirtualKeyCode::Slash),
+    ("slash", VirtualKeyCode::Slash),
+    ("\\", VirtualKeyCode::Backslash),
+    ("backslash", VirtualKeyCode::Backslash),
+    (";", VirtualKeyCode::Semicolon),
+    ("semicolon", VirtualKeyCode::Semicolon),
+    ("'", VirtualKeyCode::Apostrophe),
+    ("apostrophe", VirtualKeyCode::Apostrophe),
+    ("`", VirtualKeyCode::Grave),
+    ("grave", VirtualKeyCode::Grave),
+    ("[", VirtualKeyCode::LBracket),
+    ("]", VirtualKeyCode::RBracket),
+    ("numpad0", VirtualKeyCode::Numpad0),
+    ("numpad1", VirtualKeyCode::Numpad1),
+    ("numpad2", VirtualKeyCode::Numpad2),
+    ("numpad3", VirtualKeyCode::Numpad3),
+    ("numpad4", VirtualKeyCode::Numpad4),
+    ("numpad5", VirtualKeyCode::Numpad5),
+    ("numpad6", VirtualKeyCode::Numpad6),
+    ("numpad7", VirtualKeyCode::Numpad7),
+    ("numpad8", VirtualKeyCode::Numpad8),
+    ("numpad9", VirtualKeyCode::Numpad9),
+    ("numpad_add", VirtualKeyCode::NumpadAdd),
+    ("numpad_subtract", VirtualKeyCode::NumpadSubtract),
+    ("f1", VirtualKeyCode::F1),
+    ("f2", VirtualKeyCode::F2),
+    ("f3", VirtualKeyCode::F3),
+    ("f4", VirtualKeyCode::F4),
+    ("f5", VirtualKeyCode::F5),
+    ("f6", VirtualKeyCode::F6),
+    ("f7", VirtualKeyCode::F7),
+    ("f8", VirtualKeyCode::F8),
+    ("f9", VirtualKeyCode::F9),
+    ("f10", VirtualKeyCode::F10),
+    ("f11", VirtualKeyCode::F11),
+    ("f12", VirtualKeyCode::F12),
+];
+
+const LETTERS: [VirtualKeyCode; 26] = [
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+];
+
+const DIGITS: [VirtualKeyCode; 10] = [
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+/// A key along with the modifiers that have to be held down with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Key {
+    code: VirtualKeyCode,
+    shift: bool,
+    ctrl: bool,
+}
+
+impl Key {
+    /// Parses names like `q`, `page_down`, `shift+r` or `ctrl+shift+]`, ignoring case.
+    fn parse(name: &str) -> Option<Self> {
+        let mut rest = name.trim().to_ascii_lowercase();
+        let (mut shift, mut ctrl) = (false, false);
+        // Not split at every `+`, since that's a key too.
+        loop {
+            if let Some(key) = rest.strip_prefix("shift+") {
+                shift = true;
+                rest = key.to_string();
+            } else if let Some(key) = rest.strip_prefix("ctrl+") {
+                ctrl = true;
+                rest = key.to_string();
+            } else {
+                break;
+            }
+        }
+        let code = KEY_NAMES
+            .iter()
+            .find(|(known, _)| *known == rest)
+            .map(|&(_, code)| code)
+            .or_else(|| match *rest.as_bytes() {
+                [letter @ b'a'..=b'z'] => Some(LETTERS[usize::from(letter - b'a')]),
+                [digit @ b'0'..=b'9'] => Some(DIGITS[usize::from(digit - b'0')]),
+                _ => None,
+            })?;
+        Some(Self { code, shift, ctrl })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("ctrl+")?;
+        }
+        if self.shift {
+            f.write_str("shift+")?;
+        }
+        if let Some(&(name, _)) = KEY_NAMES.iter().find(|(_, code)| *code == self.code) {
+            return f.write_str(name);
+        }
+        if let Some(i) = LETTERS.iter().position(|&code| code == self.code) {
+            return write!(f, "{}", char::from(b'a' + i as u8));
+        }
+        if let Some(i) = DIGITS.iter().position(|&code| code == self.code) {
+            return write!(f, "{}", i);
+        }
+        write!(f, "{:?}", self.code)
+    }
+}
+
+/// What an action is bound to: a key, wherever it is on the keyboard, or a character, whichever
+/// key types it on the keyboard layout in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Binding {
+    Key(Key),
+    Character(char),
+}
+
+impl Binding {
+    /// Parses a key name, or failing that a single character such as `#`, which takes no
+    /// modifiers since they're what it's typed with.
+    fn parse(name: &str) -> Option<Self> {
+        if let Some(key) = Key::parse(name) {
+            return Some(Binding::Key(key));
+        }
+        let mut characters = name.trim().chars();
+        match (characters.next(), characters.next()) {
+            (Some(character), None) => Some(Binding::Character(character)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => key.fmt(f),
+            Binding::Character(character) => write!(f, "{}", character),
+        }
+    }
+}
+
+/// One key name or several, as actions are given in the `[keys]` table of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeyNames {
+    One(String),
+    Several(Vec<String>),
+}
+
+impl KeyNames {
+    fn names(&self) -> &[String] {
+        match self {
+            KeyNames::One(name) => std::slice::from_ref(name),
+            KeyNames::Several(names) => names,
+        }
+    }
+}
+
+/// Which action each key and character does.
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: HashMap<Binding, Action>,
+}
+
+impl Keymap {
+    /// The default bindings, with the actions in `overrides` bound to the keys given for them
+    /// instead. Action and key names that don't exist are warned about and left out.
+    pub fn new(overrides: &BTreeMap<String, KeyNames>) -> Self {
+        let mut bindings: HashMap<Binding, Action> = DEFAULT_KEYS
+            .iter()
+            .map(|&(name, action)| {
+                (
+                    Binding::parse(name).expect("default keys are valid"),
+                    action,
+                )
+            })
+            .collect();
+        for (name, keys) in overrides {
+            let action = match Action::from_name(name) {
+                Some(action) => action,
+                None => {
+                    eprintln!("Ignoring keys for `{}`, which is not an action", name);
+                    continue;
+                }
+            };
+            let given = keys.names();
+            // `none` unbinds the action just like an empty list.
+            let given = match given {
+                [only] if only.trim().eq_ignore_ascii_case("none") => &[],
+                given => given,
+            };
+            let keys: Vec<Binding> = given
+                .iter()
+                .filter_map(|key| match Binding::parse(key) {
+                    None => {
+                        eprintln!("Ignoring `{}` for `{}`, which is not a key", key, name);
+                        None
+                    }
+                    // Typing a character has no end to it.
+                    Some(Binding::Character(_)) if action == Action::Magnify => {
+                        eprintln!(
+                            "Ignoring `{}` for `{}`, which has to be held down",
+                            key, name
+                        );
+                        None
+                    }
+                    parsed => parsed,
+                })
+                .collect();
+            // An empty list unbinds the action, a list of nothing but typos leaves it be.
+            if keys.is_empty() && !given.is_empty() {
+                continue;
+            }
+            bindings.retain(|_, bound| *bound != action);
+            for key in keys {
+                bindings.insert(key, action);
+            }
+        }
+        Self { bindings }
+    }
+
+    /// The action `code` does with `modifiers` held down. Keys bound without a modifier still
+    /// work with it held, unless that combination is bound to something else, so that `=`
+    /// doesn't need shift to be `+`.
+    pub fn action(&self, code: VirtualKeyCode, modifiers: ModifiersState) -> Option<Action> {
+        let (shift, ctrl) = (modifiers.shift(), modifiers.ctrl());
+        [(shift, ctrl), (shift, false), (false, false)]
+            .into_iter()
+            .find_map(|(shift, ctrl)| {
+                let key = Binding::Key(Key { code, shift, ctrl });
+                self.bindings.get(&key).copied()
+            })
+    }
+
+    /// The action typing `character` does, with whichever key and modifiers typed it.
+    pub fn character_action(&self, character: char) -> Option<Action> {
+        self.bindings.get(&Binding::Character(character)).copied()
+    }
+
+    /// Whether `code` is bound to `action` with any modifiers, such as to tell when a key that
+    /// works while held down is let go of.
+    pub fn binds(&self, code: VirtualKeyCode, action: Action) -> bool {
+        self.bindings.iter().any(|(binding, bound)| {
+            matches!(binding, Binding::Key(key) if key.code == code) && *bound == action
+        })
+    }
+
+    /// A line for every action that's bound to a key, listing its keys and characters and then
+    /// its name.
+    pub fn help(&self) -> Vec<String> {
+        let bound: Vec<(String, &str)> = Action::NAMES
+            .iter()
+            .filter_map(|&(name, action)| {
+                let mut keys: Vec<String> = self
+                    .bindings
+                    .iter()
+                    .filter(|(_, bound)| **bound == action)
+                    .map(|(key, _)| key.to_string())
+                    .collect();
+                keys.sort();
+                (!keys.is_empty()).then(|| (keys.join(", "), name))
+            })
+            .collect();
+        let width = bound.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0);
+        bound
+            .into_iter()
+            .map(|(keys, name)| format!("{:<width$}  {}", keys, name.replace('_', " ")))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: VirtualKeyCode, shift: bool, ctrl: bool) -> Key {
+        Key { code, shift, ctrl }
+    }
+
+    fn keymap(overrides: &[(&str, &[&str])]) -> Keymap {
+        let overrides = overrides
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys.iter().map(|key| key.to_string()).collect();
+                (action.to_string(), KeyNames::Several(keys))
+            })
+            .collect();
+        Keymap::new(&overrides)
+    }
+
+    fn none() -> ModifiersState {
+        ModifiersState::empty()
+    }
+
+    #[test]
+    fn parses_modifiers_in_any_order_and_case() {
+        let expected = Some(key(VirtualKeyCode::X, true, true));
+        assert_eq!(Key::parse("ctrl+shift+x"), expected);
+        assert_eq!(Key::parse("shift+ctrl+x"), expected);
+        assert_eq!(Key::parse(" Ctrl+Shift+X "), expected);
+    }
+
+    #[test]
+    fn parses_named_keys() {
+        assert_eq!(
+            Key::parse("page_down"),
+            Some(key(VirtualKeyCode::PageDown, false, false))
+        );
+        assert_eq!(Key::parse("esc"), Key::parse("escape"));
+        assert_eq!(
+            Key::parse("f12"),
+            Some(key(VirtualKeyCode::F12, false, false))
+        );
+        assert_eq!(
+            Key::parse("7"),
+            Some(key(VirtualKeyCode::Key7, false, false))
+        );
+        // `+` is a key as well as what joins the modifiers to it.
+        assert_eq!(
+            Key::parse("shift++"),
+            Some(key(VirtualKeyCode::Plus, true, false))
+        );
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_key() {
+        for name in ["", "ctrl+", "shift+", "nonsense", "ab", "alt+x", "ctrl-x"] {
+            assert_eq!(Key::parse(name), None, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn every_default_key_is_valid() {
+        for (name, _) in DEFAULT_KEYS {
+            assert!(Binding::parse(name).is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn characters_are_bound_whichever_key_types_them() {
+        assert_eq!(Binding::parse("#"), Some(Binding::Character('#')));
+        assert_eq!(Binding::parse(" é "), Some(Binding::Character('é')));
+        // Names of keys are still keys, and characters take no modifiers.
+        assert_eq!(Binding::parse("+"), Key::parse("+").map(Binding::Key));
+        assert_eq!(Binding::parse("ctrl+#"), None);
+        assert_eq!(Binding::parse("##"), None);
+
+        let keymap = keymap(&[]);
+        assert_eq!(keymap.character_action('#'), Some(Action::PixelGrid));
+        assert_eq!(
+            keymap.action(VirtualKeyCode::Key3, ModifiersState::SHIFT),
+            None
+        );
+        // Letters are bound as keys, so typing them does nothing more.
+        assert_eq!(keymap.character_action('q'), None);
+        assert!(keymap
+            .help()
+            .iter()
+            .any(|line| line.starts_with("# ") && line.ends_with(" pixel grid")));
+    }
+
+    #[test]
+    fn characters_replace_the_defaults_too() {
+        let typed = keymap(&[("help", &["?"]), ("pixel_grid", &["g"])]);
+        assert_eq!(typed.character_action('?'), Some(Action::Help));
+        assert_eq!(typed.character_action('#'), None);
+        assert_eq!(
+            typed.action(VirtualKeyCode::G, none()),
+            Some(Action::PixelGrid)
+        );
+        // There's no telling when a character stops being held down.
+        let held = keymap(&[("magnify", &["%"])]);
+        assert_eq!(held.character_action('%'), None);
+        assert_eq!(
+            held.action(VirtualKeyCode::M, none()),
+            Some(Action::Magnify)
+        );
+    }
+
+    #[test]
+    fn bindings_replace_the_defaults() {
+        let keymap = keymap(&[("quit", &["k", "ctrl+shift+k"])]);
+        assert_eq!(keymap.action(VirtualKeyCode::K, none()), Some(Action::Quit));
+        let both = ModifiersState::CTRL | ModifiersState::SHIFT;
+        assert_eq!(keymap.action(VirtualKeyCode::K, both), Some(Action::Quit));
+        assert_eq!(keymap.action(VirtualKeyCode::Q, none()), None);
+        assert_eq!(keymap.action(VirtualKeyCode::Escape, none()), None);
+        // The rest keep their keys.
+        assert_eq!(keymap.action(VirtualKeyCode::N, none()), Some(Action::Next));
+    }
+
+    #[test]
+    fn bindings_take_keys_from_other_actions() {
+        let keymap = keymap(&[("fullscreen", &["n"])]);
+        assert_eq!(
+            keymap.action(VirtualKeyCode::N, none()),
+            Some(Action::Fullscreen)
+        );
+        assert_eq!(keymap.action(VirtualKeyCode::F, none()), None);
+    }
+
+    #[test]
+    fn none_and_nothing_unbind() {
+        let keymap = keymap(&[("fullscreen", &["none"]), ("quit", &[])]);
+        assert_eq!(keymap.action(VirtualKeyCode::F, none()), None);
+        assert_eq!(keymap.action(VirtualKeyCode::Q, none()), None);
+        assert!(keymap
+            .help()
+            .iter()
+            .all(|line| !line.ends_with(" fullscreen")));
+        let keymap = Keymap::new(&BTreeMap::from([(
+            "fullscreen".to_string(),
+            KeyNames::One("None".to_string()),
+        )]));
+        assert_eq!(keymap.action(VirtualKeyCode::F, none()), None);
+    }
+
+    #[test]
+    fn typos_fall_back_to_the_defaults() {
+        let typos = keymap(&[("quit", &["nonsense"]), ("no_such_action", &["q"])]);
+        assert_eq!(typos.action(VirtualKeyCode::Q, none()), Some(Action::Quit));
+        let mixed = keymap(&[("quit", &["nonsense", "k"])]);
+        assert_eq!(mixed.action(VirtualKeyCode::K, none()), Some(Action::Quit));
+        assert_eq!(mixed.action(VirtualKeyCode::Q, none()), None);
+    }
+
+    #[test]
+    fn shifted_keys_do_their_own_action() {
+        let keymap = keymap(&[]);
+        let shift = ModifiersState::SHIFT;
+        assert_eq!(keymap.action(VirtualKeyCode::W, none()), Some(Action::Fit));
+        assert_eq!(
+            keymap.action(VirtualKeyCode::W, shift),
+            Some(Action::Stretch)
+        );
+        assert_eq!(
+            keymap.action(VirtualKeyCode::R, none()),
+            Some(Action::RotateClockwise)
+        );
+        assert_eq!(
+            keymap.action(VirtualKeyCode::R, shift),
+            Some(Action::RotateCounterclockwise)
+        );
+    }
+
+    #[test]
+    fn modifiers_fall_back_to_the_key_alone() {
+        let keymap = keymap(&[]);
+        // `+` is shift and `=` on many layouts.
+        assert_eq!(
+            keymap.action(VirtualKeyCode::Equals, ModifiersState::SHIFT),
+            Some(Action::ZoomIn)
+        );
+        assert_eq!(
+            keymap.action(
+                VirtualKeyCode::R,
+                ModifiersState::CTRL | ModifiersState::SHIFT
+            ),
+            Some(Action::RotateCounterclockwise)
+        );
+        assert_eq!(
+            keymap.action(VirtualKeyCode::W, ModifiersState::CTRL),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(VirtualKeyCode::J, ModifiersState::SHIFT),
+            None
+        );
+    }
+}
@@ -1,35 +1,460 @@
-use std::cmp::max;
+mod archive;
+mod compare;
+mod config_file;
+mod icc;
+mod ipc;
+mod keys;
+mod montage;
+mod overlay;
+mod picture;
+mod playlist;
+mod preload;
+mod render;
+mod svg;
+mod video;
+mod window_state;
 
-use clap::Parser;
+use std::{
+    borrow::Cow,
+    env, fmt, fs, io, mem,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use arboard::{Clipboard, ImageData};
+use clap::{ArgEnum, Parser};
+use compare::{Comparison, Layout};
+use config_file::FileConfig;
+use image::{ColorType, ImageFormat, RgbaImage};
+use keys::{Action, Keymap};
+use montage::Montage;
+use overlay::{Canvas, Histogram, Minimap};
+use picture::{Frame, Source};
 use pixels::{Pixels, SurfaceTexture};
+use playlist::{Playlist, SortOrder};
+use preload::{Decoded, Preloader};
+use render::{
+    apply, draw, draw_columns, draw_pixel_grid, inset, prepare, window_size, Backdrop, Filter,
+    FitMode, Placement, Transform, View, MAX_ZOOM, MIN_ZOOM,
+};
+use serde::Deserialize;
 use thiserror::Error;
+use window_state::WindowState;
 use winit::{
-    dpi::PhysicalSize,
+    dpi::{PhysicalPosition, PhysicalSize},
     error::OsError,
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event::{
+        ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+        StartCause, VirtualKeyCode, WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    monitor::MonitorHandle,
+    window::{CursorIcon, Fullscreen, Window, WindowBuilder},
 };
 
+/// How much of the screen the window may take up at most, unless told otherwise.
 const SCREEN_PERCENT: u32 = 90;
+/// The screen a `--screenshot` is taken on, there being no real one to ask, and the one the
+/// window is sized for when no monitor can be found.
+const ASSUMED_SCREEN_SIZE: PhysicalSize<u32> = PhysicalSize::new(1920, 1080);
+/// The widest margin `--padding` keeps, far more than any screen has room for.
+const MAX_PADDING: u32 = 10_000;
+const ZOOM_STEP: f32 = 1.25;
+const PAN_STEP: f32 = 50.0;
+/// How far behind an animation may fall before it stops catching up by skipping frames, such
+/// as after the computer was asleep.
+const MAX_ANIMATION_LAG: Duration = Duration::from_secs(1);
+/// How many pixels of touchpad scrolling count as one notch of a mouse wheel.
+const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
+const BRIGHTNESS_STEP: f32 = 0.05;
+const CONTRAST_STEP: f32 = 0.1;
+const MAX_CONTRAST: f32 = 4.0;
+/// How long a first press of Delete waits for the second one that confirms it.
+const DELETE_CONFIRM_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long stepping past the first or last image says so.
+const END_NOTICE_TIME: Duration = Duration::from_secs(2);
+/// The frame around the window while a file is dragged over it.
+const DROP_HINT_THICKNESS: u32 = 4;
+const DROP_HINT_COLOR: [u8; 3] = [0x30, 0x90, 0xff];
+/// How far the cursor may move between pressing and releasing the button for it to still count
+/// as a click rather than a drag.
+const CLICK_SLOP: f64 = 4.0;
+const SELECTION_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+const SELECTION_ALPHA: u8 = 0x30;
+/// The line between the two images of `--compare`.
+const DIVIDER_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+const DIVIDER_WIDTH: u32 = 2;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Config {
-    /// Name of the image to view.
-    file_name: String,
+    /// The images to view: paths, directories, ZIP or CBZ archives, http(s) URLs or `-` to read
+    /// one from stdin. With a single image, the rest of its directory can be browsed too.
+    #[clap(required_unless_present = "from-file")]
+    files: Vec<String>,
+
+    /// Also view the images listed in the file PATH, one path per line, or on stdin for `-`.
+    /// Listed files that don't exist are skipped.
+    #[clap(long, value_name = "PATH")]
+    from_file: Option<PathBuf>,
+
+    /// Also view the images in subdirectories of the directories given.
+    #[clap(short, long)]
+    recursive: bool,
+
+    /// The order images found in a directory are viewed in
+    #[clap(long, arg_enum, default_value = "natural")]
+    sort: SortOrder,
+
+    /// How the image is scaled to the window [default: contain]
+    #[clap(long, arg_enum)]
+    fit: Option<FitMode>,
+
+    /// Fit images to the width of the window and scroll down through them with the wheel or the
+    /// arrow keys, starting at the top. Images at least 3 times taller than wide are shown like
+    /// this anyway when they'd otherwise be fit to contain.
+    #[clap(long)]
+    tall: bool,
+
+    /// Distort images to fill the window exactly, ignoring their aspect ratio, for textures and
+    /// the like. `W` with shift toggles this.
+    #[clap(long)]
+    stretch: bool,
+
+    /// Color of the window around the image, as hex RGB [default: #000000]
+    #[clap(long)]
+    background: Option<Color>,
+
+    /// Show transparent areas over the background color instead of a checkerboard.
+    #[clap(long)]
+    no_checker: bool,
+
+    /// Display images as stored, ignoring the EXIF orientation tag.
+    #[clap(long)]
+    no_autorotate: bool,
+
+    /// Print the format, dimensions, color type, bit depth and size of the images instead of
+    /// showing them.
+    #[clap(long)]
+    info: bool,
+
+    /// Advance to the next image every SECONDS, stopping at the last one without --loop.
+    #[clap(long, value_name = "SECONDS", parse(try_from_str = parse_seconds))]
+    slideshow: Option<Duration>,
+
+    /// Go on to the first image after the last one, and to the last before the first, instead
+    /// of stopping at either end.
+    #[clap(long = "loop")]
+    looping: bool,
+
+    /// What advancing past the last image does, such as at the end of a slideshow
+    #[clap(
+        long,
+        arg_enum,
+        value_name = "ACTION",
+        default_value = "stop",
+        conflicts_with = "looping"
+    )]
+    on_end: OnEnd,
+
+    /// Start with a contact sheet of all the images, COLUMNS thumbnails wide. Clicking one opens
+    /// it, Tab goes back to the sheet.
+    #[clap(long, value_name = "COLUMNS", parse(try_from_str = parse_columns))]
+    grid: Option<u32>,
+
+    /// The largest the window may get, in pixels, such as 1280x720 [default: a share of the
+    /// screen, see --screen-percent]
+    #[clap(long, value_name = "WIDTHxHEIGHT", parse(try_from_str = parse_size))]
+    max_window: Option<PhysicalSize<u32>>,
+
+    /// How much of the screen the window may take up at most [default: 90]
+    #[clap(long, value_name = "PERCENT", parse(try_from_str = parse_percent))]
+    screen_percent: Option<u32>,
+
+    /// How the image is sampled when zoomed [default: auto]
+    #[clap(long, arg_enum)]
+    filter: Option<Filter>,
+
+    /// Turn the images clockwise by DEGREES: 90, 180 or 270.
+    #[clap(long, value_name = "DEGREES", parse(try_from_str = parse_rotation), default_value = "0")]
+    rotate: u8,
+
+    /// Instead of opening a window, write what it would first show to the image file OUT.
+    /// Without --max-window, the window is sized as if the screen were 1920x1080.
+    #[clap(long, value_name = "OUT")]
+    screenshot: Option<PathBuf>,
+
+    /// Program that `e` opens the current file with, followed by any arguments it needs
+    /// [default: $IMG_VWR_EDITOR, or the system's default application]
+    #[clap(long, value_name = "COMMAND")]
+    editor: Option<String>,
+
+    /// Keep a margin of PIXELS of the background color around the image when fitting it to the
+    /// window. It gets thinner if the window gets too small for it.
+    #[clap(long, value_name = "PIXELS", parse(try_from_str = parse_padding), default_value = "0")]
+    padding: u32,
+
+    /// Take commands from scripts on a Unix socket created at SOCKET, one per line: `next`,
+    /// `prev`, `zoom LEVEL`, `open PATH` and `quit`. Each is answered with `ok` or an `error: `
+    /// line.
+    #[clap(long, value_name = "SOCKET")]
+    ipc: Option<PathBuf>,
+
+    /// The window title, with `{name}` standing for the file name, `{path}` for all of it,
+    /// `{w}` and `{h}` for the size of the image, `{zoom}` for the zoom in percent, `{pos}` for
+    /// the position in the list such as `3/57`, and `{index}` and `{count}` for its two halves
+    /// [default: the path, the rotation and the position]
+    #[clap(long, value_name = "TEMPLATE")]
+    title: Option<String>,
+
+    /// Keep the window above all others. `t` toggles this.
+    #[clap(long)]
+    always_on_top: bool,
+
+    /// Start out at zoom FACTOR, 1 being actual size, instead of fitting the image.
+    #[clap(long, value_name = "FACTOR", parse(try_from_str = parse_zoom))]
+    zoom: Option<f32>,
+
+    /// Start out with the image moved X pixels right and Y pixels down from the middle, as far
+    /// as there's image to see, such as `--pan -200,100`.
+    #[clap(long, value_name = "X,Y", parse(try_from_str = parse_pan), allow_hyphen_values = true)]
+    pan: Option<(f32, f32)>,
+
+    /// Redraw animations, and zooming or panning by a step, at most FPS times a second.
+    /// Animations that are faster skip frames to keep their speed.
+    #[clap(long, value_name = "FPS", parse(try_from_str = parse_fps), default_value = "60")]
+    max_fps: u32,
+
+    /// Jump straight to the new zoom or position when zooming or panning by a step, instead of
+    /// gliding there.
+    #[clap(long)]
+    no_animation: bool,
+
+    /// Log how long decoding and drawing take, and how images are sized, to stderr. RUST_LOG
+    /// can pick other levels.
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Leave placing the window to the window manager, instead of centering it on the screen.
+    #[clap(long)]
+    no_center: bool,
+
+    /// Show the two images given next to each other, split by a divider that can be dragged
+    /// with the mouse. `o` switches to showing one at a time, to flip between them. Both are
+    /// zoomed, panned, turned and adjusted together.
+    #[clap(long)]
+    compare: bool,
+
+    /// Show the first frame of the videos given, extracted with ffmpeg, instead of refusing
+    /// them.
+    #[clap(long)]
+    video_preview: bool,
+
+    /// Start in borderless fullscreen.
+    #[clap(short, long)]
+    fullscreen: bool,
+}
+
+impl Config {
+    /// Fills in whatever wasn't given on the command line from the config file.
+    fn merge(mut self, file: FileConfig) -> Self {
+        self.fit = self.fit.or(file.fit);
+        self.background = self.background.or(file.background);
+        self.no_checker |= file.checker == Some(false);
+        self.no_center |= file.center == Some(false);
+        self.slideshow = self.slideshow.or_else(|| file.slideshow());
+        self
+    }
+
+    /// What's behind transparent pixels: --background, or a checkerboard unless --no-checker.
+    fn backdrop(&self) -> Backdrop {
+        if self.no_checker {
+            Backdrop::Solid(self.background.unwrap_or_default())
+        } else {
+            Backdrop::Checkerboard
+        }
+    }
+
+    /// What every image starts out with.
+    fn initial_transform(&self) -> Transform {
+        Transform {
+            rotation: self.rotate,
+            ..Transform::default()
+        }
+    }
+}
+
+/// A move through the playlist.
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Next,
+    Prev,
+    /// Load whatever the playlist is on now, after it changed under the displayed image.
+    Stay,
+    /// Show the contact sheet of all images instead of a single one.
+    Grid,
+}
+
+/// What advancing past the last image does, without --loop.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OnEnd {
+    /// Stay on the last image.
+    Stop,
+    /// Close the window.
+    Quit,
+}
+
+/// What the background threads wake up the event loop with.
+#[derive(Debug)]
+enum UserEvent {
+    Decoded(Decoded),
+    Command(ipc::Request),
+}
+
+impl From<Decoded> for UserEvent {
+    fn from(decoded: Decoded) -> Self {
+        UserEvent::Decoded(decoded)
+    }
+}
+
+impl From<ipc::Request> for UserEvent {
+    fn from(request: ipc::Request) -> Self {
+        UserEvent::Command(request)
+    }
+}
+
+/// The formats `s` can save the displayed image in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SaveFormat {
+    Png,
+    Jpeg,
+    WebP,
+}
+
+impl SaveFormat {
+    /// The format after this one when cycling through all of them.
+    fn next(self) -> Self {
+        match self {
+            SaveFormat::Png => SaveFormat::Jpeg,
+            SaveFormat::Jpeg => SaveFormat::WebP,
+            SaveFormat::WebP => SaveFormat::Png,
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            SaveFormat::Png => ImageFormat::Png,
+            SaveFormat::Jpeg => ImageFormat::Jpeg,
+            SaveFormat::WebP => ImageFormat::WebP,
+        }
+    }
+
+    /// Whether the format can store transparency. Images saved in one that can't are flattened
+    /// onto the background color first, never onto the checkerboard.
+    fn has_alpha(self) -> bool {
+        self != SaveFormat::Jpeg
+    }
+}
+
+/// An opaque RGB color.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(try_from = "String")]
+struct Color([u8; 3]);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl Color {
+    fn to_rgba(self) -> [u8; 4] {
+        let Color([r, g, b]) = self;
+        [r, g, b, 0xff]
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    /// Parses `#rrggbb` or the `#rgb` shorthand, the `#` being optional.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        let invalid = || format!("`{}` is not a hex color like #202020", s);
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+        match hex.len() {
+            6 => Ok(Color([
+                channel(&hex[0..2])?,
+                channel(&hex[2..4])?,
+                channel(&hex[4..6])?,
+            ])),
+            3 => Ok(Color([
+                channel(&hex[0..1])? * 0x11,
+                channel(&hex[1..2])? * 0x11,
+                channel(&hex[2..3])? * 0x11,
+            ])),
+            _ => Err(invalid()),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
 enum RvuError {
-    #[error("An error occurred while loading the image.")]
+    #[error("An error occurred while loading the image: {0}")]
     IoError(#[from] std::io::Error),
 
-    #[error("An error occurred while processing the image.")]
+    #[error("An error occurred while processing the image: {0}")]
     ImageError(#[from] image::ImageError),
 
-    #[error("Unable to calculate maximum screen size of your primary monitor.")]
-    NoPrimaryMonitor,
+    #[error("{name}: {error}")]
+    InFile { name: String, error: Box<RvuError> },
+
+    #[error("Nothing was piped in on stdin.")]
+    EmptyStdin,
+
+    #[error("There are no images in {}.", .0.display())]
+    NoImages(PathBuf),
+
+    #[error("None of the images listed in {} exist.", .0.display())]
+    NoListedImages(PathBuf),
+
+    #[error("--compare takes exactly two images, not {0}.")]
+    CompareNeedsTwo(usize),
+
+    #[error("The image contains no pixels.")]
+    EmptyImage,
+
+    #[error("An error occurred while rendering the SVG: {0}")]
+    SvgError(#[from] resvg::usvg::Error),
+
+    #[error("Unable to read the archive: {0}")]
+    ArchiveError(#[from] zip::result::ZipError),
+
+    #[error("The image unpacks to more than the {} MiB an image in an archive may take.", .0 >> 20)]
+    EntryTooLarge(u64),
+
+    #[error("Videos can only be shown with --video-preview, which shows their first frame.")]
+    VideoNotSupported,
+
+    #[error("Showing the first frame of a video needs ffmpeg, which isn't installed.")]
+    NoFfmpeg,
+
+    #[error("ffmpeg couldn't extract the first frame of the video: {0}")]
+    VideoError(String),
+
+    #[error("Unable to download the image: {0}")]
+    NetworkError(Box<ureq::Error>),
+
+    #[error("Invalid config file {}: {message}", path.display())]
+    ConfigError { path: PathBuf, message: String },
 
     #[error("Unable to create window.")]
     WindowError(#[from] OsError),
@@ -38,96 +463,2117 @@ enum RvuError {
     PixelError(#[from] pixels::Error),
 }
 
+impl RvuError {
+    /// Names the file or URL the error is about.
+    fn in_file(self, name: impl fmt::Display) -> Self {
+        RvuError::InFile {
+            name: name.to_string(),
+            error: Box::new(self),
+        }
+    }
+}
+
+impl From<ureq::Error> for RvuError {
+    fn from(err: ureq::Error) -> Self {
+        // Boxed since it's several times the size of every other error.
+        RvuError::NetworkError(Box::new(err))
+    }
+}
+
 type Result<T> = std::result::Result<T, RvuError>;
 
-fn main() -> Result<()> {
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
     let config = Config::parse();
+    env_logger::Builder::new()
+        .filter_level(if config.verbose {
+            log::LevelFilter::Debug
+        } else {
+            log::LevelFilter::Warn
+        })
+        .parse_default_env()
+        .init();
+    // Asking for a particular size wins over remembering the last one.
+    let sized_by_flags = config.fit.is_some()
+        || config.zoom.is_some()
+        || config.max_window.is_some()
+        || config.screen_percent.is_some();
+    let file_config = FileConfig::load()?;
+    let keymap = Keymap::new(&file_config.keys);
+    let config = config.merge(file_config);
 
-    let image = image::io::Reader::open(&config.file_name)?.decode()?;
-    let event_loop = EventLoop::new();
-    let primary_monitor = event_loop
-        .primary_monitor()
-        .ok_or(RvuError::NoPrimaryMonitor)?;
-    let screen_size = primary_monitor.size();
-    let max_screen_size = (
-        screen_size.width * SCREEN_PERCENT / 100,
-        screen_size.height * SCREEN_PERCENT / 100,
-    );
+    let mut sources = Vec::new();
+    let mut scanned_dir = false;
+    for file_name in &config.files {
+        match file_name.as_str() {
+            "-" => sources.push(Source::stdin()?),
+            // Download errors already name the URL.
+            url if picture::is_url(url) => sources.push(Source::url(url)?),
+            _ => {
+                let path = PathBuf::from(file_name);
+                if path.is_dir() {
+                    let paths = playlist::scan_dir(&path, config.recursive, config.sort)
+                        .map_err(|err| RvuError::from(err).in_file(file_name))?;
+                    if paths.is_empty() {
+                        return Err(RvuError::NoImages(path));
+                    }
+                    sources.extend(paths.into_iter().map(Source::File));
+                    scanned_dir = true;
+                } else if archive::is_archive(&path) {
+                    let images =
+                        archive::images(&path).map_err(|err| err.in_file(path.display()))?;
+                    if images.is_empty() {
+                        return Err(RvuError::NoImages(path));
+                    }
+                    sources.extend(images);
+                } else if config.video_preview && video::is_video(&path) {
+                    let bytes =
+                        video::first_frame(&path).map_err(|err| err.in_file(path.display()))?;
+                    sources.push(Source::Memory {
+                        name: file_name.clone(),
+                        bytes: bytes.into(),
+                    });
+                } else {
+                    sources.push(Source::File(path));
+                }
+            }
+        }
+    }
+    if let Some(list) = &config.from_file {
+        let paths =
+            playlist::read_list(list).map_err(|err| RvuError::from(err).in_file(list.display()))?;
+        for path in paths {
+            if path.is_file() {
+                sources.push(Source::File(path));
+            } else {
+                eprintln!("Skipping {}: no such file", path.display());
+            }
+        }
+        if sources.is_empty() {
+            return Err(RvuError::NoListedImages(list.clone()));
+        }
+    }
 
-    // Calculate the scale
-    let horz_scale = calc_scale(max_screen_size.0, image.width());
-    let vert_scale = calc_scale(max_screen_size.1, image.height());
-    let scale = max(horz_scale, vert_scale);
+    if config.info {
+        let mut failed = false;
+        for source in &sources {
+            match picture::info(source) {
+                Ok(info) => println!("{}:\n{}", source, info),
+                Err(err) => {
+                    eprintln!("{}: {}", source, err);
+                    failed = true;
+                }
+            }
+        }
+        if failed {
+            process::exit(1);
+        }
+        return Ok(());
+    }
 
-    let window_inner_size = PhysicalSize::new(image.width() / scale, image.height() / scale);
+    // The second image to compare isn't stepped to, it's shown next to the first one.
+    let compared = if config.compare {
+        if sources.len() != 2 {
+            return Err(RvuError::CompareNeedsTwo(sources.len()));
+        }
+        sources.pop()
+    } else {
+        None
+    };
+    let playlist = match sources.as_slice() {
+        [Source::File(path)] if !scanned_dir && config.from_file.is_none() && !config.compare => {
+            Playlist::from_siblings(path, config.sort)
+                .map_err(|err| RvuError::from(err).in_file(path.display()))?
+        }
+        _ => Playlist::new(sources),
+    };
+    let autorotate = !config.no_autorotate;
+    let background = config.background.unwrap_or_default();
+    let backdrop = config.backdrop();
+    let initial_transform = config.initial_transform();
+    if let Some(out) = &config.screenshot {
+        return screenshot(
+            playlist.current(),
+            out,
+            &config,
+            initial_transform,
+            backdrop,
+        );
+    }
 
-    let window = WindowBuilder::new()
-        .with_title(&config.file_name)
-        .with_inner_size(window_inner_size)
-        .build(&event_loop)?;
+    let comparison = compared.map(|source| {
+        let (mut frames, _) = load_or_placeholder(&source, autorotate);
+        Comparison::new(
+            source,
+            frames.swap_remove(0).buffer,
+            initial_transform,
+            backdrop,
+        )
+    });
+    // Built when it's first shown, and again after the playlist changes.
+    let mut montage = None;
+    let in_grid = config.grid.is_some();
+    if let Some(columns) = config.grid {
+        montage = Some(build_montage(
+            &playlist, columns, autorotate, background, backdrop,
+        ));
+    }
+    // The window is sized from the header alone, the pixels are decoded once it's up. If that
+    // turns out wrong, say because of the EXIF orientation, the window is resized after all.
+    let expected_size = match &montage {
+        Some(montage) => montage.frame().buffer.dimensions(),
+        None => picture::info(playlist.current())
+            .ok()
+            .map(|info| (info.width, info.height))
+            .filter(|&(width, height)| width > 0 && height > 0)
+            .unwrap_or(picture::PLACEHOLDER_SIZE),
+    };
+    let loading = montage.is_none();
+    let event_loop = EventLoop::<UserEvent>::with_user_event();
+    // The window opens on the primary monitor, unless it's put back where it was last time. Some
+    // Wayland compositors and headless setups have no primary monitor, or no monitor at all.
+    let monitor = event_loop.primary_monitor().or_else(|| {
+        let monitor = event_loop.available_monitors().next();
+        if let Some(monitor) = &monitor {
+            log::warn!(
+                "There's no primary monitor, sizing the window for {}",
+                monitor.name().as_deref().unwrap_or("the first one")
+            );
+        }
+        monitor
+    });
+    let (screen_size, screen_scale) = match &monitor {
+        Some(monitor) => (monitor.size(), monitor.scale_factor() as f32),
+        None => {
+            log::warn!(
+                "No monitor found, sizing the window for a {}x{} screen",
+                ASSUMED_SCREEN_SIZE.width,
+                ASSUMED_SCREEN_SIZE.height
+            );
+            (ASSUMED_SCREEN_SIZE, 1.0)
+        }
+    };
+    let max_screen_size = max_window_size(&config, screen_size);
 
-    let surface = SurfaceTexture::new(window_inner_size.width, window_inner_size.height, &window);
-    let mut pixels = Pixels::new(image.width(), image.height(), surface)?;
+    // Size the window to the scaled image, as far as the screen allows.
+    let fit = config.fit.unwrap_or_default();
+    let tall = config.tall;
+    let padding = config.padding;
+    let zoom = match config.zoom {
+        Some(zoom) => (zoom * screen_scale).clamp(MIN_ZOOM, MAX_ZOOM),
+        None => window_zoom(
+            fit,
+            tall,
+            max_screen_size,
+            expected_size,
+            padding,
+            screen_scale,
+        ),
+    };
+    let saved_state = if sized_by_flags || config.fullscreen {
+        None
+    } else {
+        WindowState::load()
+    };
+    let window_inner_size = match saved_state {
+        Some(state) => state.size(),
+        None => window_size(expected_size, zoom, max_screen_size, padding),
+    };
+    // A remembered window keeps its size whatever the image turns out to be.
+    let initial_size = (loading && saved_state.is_none()).then_some(expected_size);
 
-    let image_bytes = image.as_rgb8().unwrap().as_flat_samples();
-    let image_bytes = image_bytes.as_slice();
+    let (frames, load_error): (_, Option<String>) = match &montage {
+        Some(montage) => (vec![montage.frame()], None),
+        None => (
+            picture::loading(window_inner_size.width, window_inner_size.height),
+            None,
+        ),
+    };
+    let image = prepare(&frames[0].buffer, initial_transform, backdrop);
+
+    let window_title = if in_grid {
+        grid_title(&playlist)
+    } else {
+        title(&playlist, initial_transform.rotation, None)
+    };
+    let mut builder = WindowBuilder::new()
+        .with_title(&window_title)
+        .with_inner_size(window_inner_size)
+        .with_always_on_top(config.always_on_top)
+        .with_fullscreen(config.fullscreen.then(|| Fullscreen::Borderless(None)));
+    // The monitor it was on may be gone by now.
+    let saved_position = saved_state
+        .map(|state| state.position())
+        .filter(|&position| is_on_screen(&event_loop, position));
+    if let Some(position) = saved_position {
+        builder = builder.with_position(position);
+    }
+    let window = builder.build(&event_loop)?;
+    // A remembered window goes back where it was, or to the middle if that's no longer on screen.
+    if saved_position.is_none() && (saved_state.is_some() || !config.no_center) {
+        if let Some(monitor) = monitor.or_else(|| window.current_monitor()) {
+            center(&window, &monitor, window_inner_size);
+        }
+    }
 
-    let pixels_bytes = pixels.get_frame();
+    let surface = SurfaceTexture::new(window_inner_size.width, window_inner_size.height, &window);
+    let mut pixels = Pixels::new(window_inner_size.width, window_inner_size.height, surface)?;
 
-    image_bytes
-        .chunks_exact(3)
-        .zip(pixels_bytes.chunks_exact_mut(4))
-        .for_each(|(image_pixel, pixel)| {
-            pixel[0] = image_pixel[0];
-            pixel[1] = image_pixel[1];
-            pixel[2] = image_pixel[2];
-            pixel[3] = 0xff;
-        });
+    let mut view = View {
+        stretch: config.stretch,
+        ..View::new(1.0, padding, window.scale_factor() as f32)
+    };
+    if !loading {
+        view.refit(
+            fit.for_image(tall, image.dimensions()),
+            window_inner_size,
+            &image,
+        );
+        view.start_at(config.zoom, config.pan, window_inner_size, &image);
+    }
+    let mut preloader = Preloader::new(event_loop.create_proxy(), autorotate);
+    if let Some(socket) = &config.ipc {
+        ipc::listen(socket, event_loop.create_proxy())
+            .map_err(|err| RvuError::from(err).in_file(socket.display()))?;
+    }
+    if loading {
+        preloader.prefetch(&with_current(&playlist));
+    } else {
+        preloader.prefetch(&playlist.neighbors());
+    }
 
-    println!(
-        "Window size: ({}, {})",
-        window_inner_size.width, window_inner_size.height
+    log::info!(
+        "Window size {}x{}, image size {}x{}, zoom {:.0}% to fit {:?}",
+        window_inner_size.width,
+        window_inner_size.height,
+        expected_size.0,
+        expected_size.1,
+        zoom * 100.0,
+        fit
     );
-    println!("Backbuffer size: ({}, {})", image.width(), image.height());
+
+    let mut viewer = Viewer {
+        max_screen_size,
+        comparison,
+        montage,
+        in_grid,
+        loading,
+        initial_size,
+        next_frame_at: Instant::now() + frames[0].delay,
+        frames,
+        load_error,
+        image,
+        window_title,
+        view,
+        start_pending: loading,
+        ..Viewer::new(config, keymap, playlist)
+    };
 
     event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Wait;
+        // Set by whatever moves through the playlist, which happens once the event is handled.
+        let mut step = None;
+
         match event {
+            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => step = viewer.tick(&window),
             Event::WindowEvent { window_id, event } if window_id == window.id() => match event {
                 WindowEvent::Resized(size) => {
                     resize(&mut pixels, &size);
+                    viewer.resized(size);
+                    window.request_redraw();
                 }
-                WindowEvent::CloseRequested
-                | WindowEvent::KeyboardInput {
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Released,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } if viewer.keymap.binds(key, Action::Magnify) => {
+                    viewer.magnifying = false;
+                    window.request_redraw();
+                }
+                // A key let go of elsewhere never reports being released here.
+                WindowEvent::Focused(false) if viewer.magnifying => {
+                    viewer.magnifying = false;
+                    window.request_redraw();
+                }
+                WindowEvent::KeyboardInput {
                     input:
                         KeyboardInput {
                             state: ElementState::Pressed,
-                            virtual_keycode: Some(VirtualKeyCode::Escape),
+                            virtual_keycode: Some(key),
                             ..
                         },
                     ..
-                } => *control_flow = ControlFlow::Exit,
-                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                } => step = viewer.press(key, &window, control_flow),
+                WindowEvent::ReceivedCharacter(character) => {
+                    step = viewer.type_character(character, &window, control_flow);
+                }
+                WindowEvent::ModifiersChanged(state) => viewer.modifiers = state,
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => step = viewer.click(state, &window),
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Right,
+                    ..
+                } => viewer.select(state, &window),
+                WindowEvent::CursorMoved { position, .. } => viewer.move_cursor(position, &window),
+                WindowEvent::MouseWheel { delta, .. } => viewer.scroll(delta, &window),
+                WindowEvent::HoveredFile(_) => {
+                    viewer.hovering_file = true;
+                    window.request_redraw();
+                }
+                WindowEvent::HoveredFileCancelled => {
+                    viewer.hovering_file = false;
+                    window.request_redraw();
+                }
+                WindowEvent::DroppedFile(path) => {
+                    viewer.hovering_file = false;
+                    step = Some(viewer.open(path));
+                }
+                WindowEvent::ScaleFactorChanged {
+                    scale_factor,
+                    new_inner_size,
+                } => {
                     resize(&mut pixels, new_inner_size);
+                    viewer.rescale(scale_factor as f32, *new_inner_size);
+                    window.request_redraw();
                 }
                 _ => {}
             },
-            Event::RedrawRequested(_) => {
-                let _ = pixels.render();
+            Event::RedrawRequested(_) => viewer.repaint(&window, &mut pixels),
+            Event::LoopDestroyed => {
+                if let Some(socket) = &viewer.config.ipc {
+                    let _ = fs::remove_file(socket);
+                }
+                // A fullscreen window says nothing about where the user wants it.
+                let position = window
+                    .outer_position()
+                    .ok()
+                    .filter(|_| window.fullscreen().is_none());
+                if let Some(position) = position {
+                    let size = window.inner_size();
+                    let state = WindowState {
+                        x: position.x,
+                        y: position.y,
+                        width: size.width,
+                        height: size.height,
+                    };
+                    if let Err(err) = state.save() {
+                        eprintln!("Unable to remember the window position: {}", err);
+                    }
+                }
+            }
+            Event::UserEvent(UserEvent::Decoded(decoded)) => {
+                preloader.receive(decoded);
+                if viewer.loading && preloader.is_ready(viewer.playlist.current()) {
+                    step = Some(Step::Stay);
+                }
+            }
+            Event::UserEvent(UserEvent::Command(request)) => {
+                let result = match &request.command {
+                    ipc::Command::Next => {
+                        step = Some(Step::Next);
+                        Ok(())
+                    }
+                    ipc::Command::Prev => {
+                        step = Some(Step::Prev);
+                        Ok(())
+                    }
+                    ipc::Command::Zoom(zoom) => {
+                        viewer.view.zoom_by(zoom / viewer.view.perceived_zoom());
+                        viewer.view.clamp(window.inner_size(), &viewer.image);
+                        window.request_redraw();
+                        Ok(())
+                    }
+                    ipc::Command::Open(path) if !path.exists() => {
+                        Err(format!("{}: no such file or directory", path.display()))
+                    }
+                    ipc::Command::Open(path) => {
+                        step = Some(viewer.open(path.clone()));
+                        Ok(())
+                    }
+                    ipc::Command::Quit => {
+                        *control_flow = ControlFlow::Exit;
+                        Ok(())
+                    }
+                };
+                request.reply(result);
+            }
+            Event::RedrawEventsCleared => {
+                *control_flow = match viewer.deadline() {
+                    Some(deadline) => ControlFlow::WaitUntil(deadline),
+                    None => ControlFlow::Wait,
+                };
             }
             _ => {}
         }
+
+        if let Some(step) = step {
+            viewer.go(step, &window, &mut preloader, control_flow);
+        }
+        if let Some(comparison) = &mut viewer.comparison {
+            comparison.follow(viewer.transform, viewer.backdrop);
+        }
+        viewer.rerasterize(&window);
     });
 }
 
-fn calc_scale(max_size: u32, current_size: u32) -> u32 {
-    if max_size >= current_size {
-        1
+/// What the window shows and everything that's been done to it, kept by the event loop.
+struct Viewer {
+    config: Config,
+    keymap: Keymap,
+    autorotate: bool,
+    /// Whether zooming and panning glide to where they're going rather than jump there.
+    animate: bool,
+    background: Color,
+    backdrop: Backdrop,
+    /// What every image starts out with.
+    initial_transform: Transform,
+    max_screen_size: PhysicalSize<u32>,
+    /// How long at least goes by between redraws of anything moving, from --max-fps.
+    frame_interval: Duration,
+    playlist: Playlist,
+    comparison: Option<Comparison>,
+    /// Built when it's first shown, and again after the playlist changes.
+    montage: Option<Montage>,
+    in_grid: bool,
+    /// While the current image is being decoded, with the previous one still up.
+    loading: bool,
+    fit: FitMode,
+    tall: bool,
+    padding: u32,
+    filter: Filter,
+    /// The size of the image the window was opened for, until the first one is decoded.
+    initial_size: Option<(u32, u32)>,
+    frames: Vec<Frame>,
+    frame_index: usize,
+    load_error: Option<String>,
+    /// Applied to the current frame to get the displayed `image`.
+    transform: Transform,
+    image: RgbaImage,
+    /// Counted from the displayed image the first time it's shown, while `show_histogram` is on.
+    histogram: Option<Histogram>,
+    /// Shown in the corner while the image doesn't fit in the window.
+    minimap: Option<Minimap>,
+    /// Kept up to date whenever the window is redrawn.
+    window_title: String,
+    view: View,
+    /// Whether --zoom and --pan are still to be applied, to the first image shown.
+    start_pending: bool,
+    cursor: PhysicalPosition<f64>,
+    dragging: bool,
+    dragging_divider: bool,
+    dragging_minimap: bool,
+    /// Where the left button went down, to tell clicks from drags.
+    pressed_at: PhysicalPosition<f64>,
+    modifiers: ModifiersState,
+    /// When the next frame of an animation is due.
+    next_frame_at: Instant,
+    next_slide_at: Option<Instant>,
+    last_redraw: Instant,
+    /// Pauses both animations and the slideshow.
+    paused: bool,
+    show_info: bool,
+    show_help: bool,
+    /// Lines between the pixels once they're zoomed in far enough.
+    pixel_grid: bool,
+    always_on_top: bool,
+    show_histogram: bool,
+    /// Created on first use. It has to stay alive for what was copied to stay on the clipboard
+    /// on some platforms.
+    clipboard: Option<Clipboard>,
+    save_format: SaveFormat,
+    /// Until when a press of Delete moves the current file to the trash.
+    confirm_delete_until: Option<Instant>,
+    /// Until when the status bar says there's nothing past the current image.
+    end_notice_until: Option<Instant>,
+    hovering_file: bool,
+    /// While `m` is held down.
+    magnifying: bool,
+    /// Two corners of the area to crop to, in pixels of the displayed image, dragged out with
+    /// the right mouse button.
+    selection: Option<((f32, f32), (f32, f32))>,
+    selecting: bool,
+    /// The color last picked with `,`, for the info bar.
+    picked_color: Option<String>,
+    /// The name of the ICC profile of the current image, for the info bar.
+    color_profile: Option<String>,
+    /// How many times the size it declares an SVG is rasterized at.
+    raster_scale: f32,
+    /// Whether the current image is an SVG, worked out once when it's loaded rather than on
+    /// every event.
+    vector: bool,
+}
+
+impl Viewer {
+    /// A viewer of `playlist` set up as `config` says, before anything is shown: the window's
+    /// size and the first image are filled in by whoever opens the window.
+    fn new(config: Config, keymap: Keymap, playlist: Playlist) -> Self {
+        let initial_transform = config.initial_transform();
+        let cursor = PhysicalPosition::new(0.0, 0.0);
+        Viewer {
+            keymap,
+            autorotate: !config.no_autorotate,
+            animate: !config.no_animation,
+            background: config.background.unwrap_or_default(),
+            backdrop: config.backdrop(),
+            initial_transform,
+            max_screen_size: ASSUMED_SCREEN_SIZE,
+            frame_interval: Duration::from_secs_f64(1.0 / f64::from(config.max_fps)),
+            playlist,
+            comparison: None,
+            montage: None,
+            in_grid: false,
+            loading: false,
+            fit: config.fit.unwrap_or_default(),
+            tall: config.tall,
+            padding: config.padding,
+            filter: config.filter.unwrap_or_default(),
+            initial_size: None,
+            frames: Vec::new(),
+            frame_index: 0,
+            load_error: None,
+            transform: initial_transform,
+            image: RgbaImage::new(0, 0),
+            histogram: None,
+            minimap: None,
+            window_title: String::new(),
+            view: View {
+                stretch: config.stretch,
+                ..View::new(1.0, config.padding, 1.0)
+            },
+            start_pending: false,
+            cursor,
+            dragging: false,
+            dragging_divider: false,
+            dragging_minimap: false,
+            pressed_at: cursor,
+            modifiers: ModifiersState::empty(),
+            next_frame_at: Instant::now(),
+            next_slide_at: config.slideshow.map(|interval| Instant::now() + interval),
+            last_redraw: Instant::now(),
+            paused: false,
+            show_info: false,
+            show_help: false,
+            pixel_grid: false,
+            always_on_top: config.always_on_top,
+            show_histogram: false,
+            clipboard: None,
+            save_format: SaveFormat::Png,
+            confirm_delete_until: None,
+            end_notice_until: None,
+            hovering_file: false,
+            magnifying: false,
+            selection: None,
+            selecting: false,
+            picked_color: None,
+            color_profile: None,
+            raster_scale: 1.0,
+            vector: false,
+            config,
+        }
+    }
+
+    /// Replaces the playlist with the one for a file or directory dropped onto the window or
+    /// sent over the socket. What was shown next to the old image, or of all of them, goes too.
+    fn open(&mut self, path: PathBuf) -> Step {
+        self.playlist = open_dropped(path, self.config.recursive, self.config.sort);
+        self.comparison = None;
+        self.montage = None;
+        Step::Stay
+    }
+
+    /// Applies `transform` to the current frame again, and drops what was worked out from the
+    /// image it replaces.
+    fn refresh(&mut self) {
+        self.image = prepare(
+            &self.frames[self.frame_index].buffer,
+            self.transform,
+            self.backdrop,
+        );
+        self.histogram = None;
+        self.minimap = None;
+    }
+
+    /// Fits the image to a window of `size` the way `fit` says, starting over from the middle.
+    fn refit(&mut self, size: PhysicalSize<u32>) {
+        let mode = self.fit.for_image(self.tall, self.image.dimensions());
+        self.view.refit(mode, size, &self.image);
+    }
+
+    /// Moves an animation on to the frame that's due and takes down the notices whose time is up,
+    /// and steps to the next image once the slideshow says so.
+    fn tick(&mut self, window: &Window) -> Option<Step> {
+        let now = Instant::now();
+        if !self.paused && picture::is_animation(&self.frames) && now >= self.next_frame_at {
+            if now > self.next_frame_at + MAX_ANIMATION_LAG {
+                self.next_frame_at = now;
+            }
+            // Frames due while redraws were held back by --max-fps are skipped, so that the
+            // animation keeps its speed.
+            while now >= self.next_frame_at {
+                self.frame_index = (self.frame_index + 1) % self.frames.len();
+                self.next_frame_at += self.frames[self.frame_index].delay;
+            }
+            self.refresh();
+            window.request_redraw();
+        }
+        if self.confirm_delete_until.is_some_and(|until| now >= until) {
+            self.confirm_delete_until = None;
+            window.request_redraw();
+        }
+        if self.end_notice_until.is_some_and(|until| now >= until) {
+            self.end_notice_until = None;
+            window.request_redraw();
+        }
+        if self.view.glide.is_some() {
+            window.request_redraw();
+        }
+        (!self.paused && self.next_slide_at.is_some_and(|at| now >= at)).then_some(Step::Next)
+    }
+
+    /// Keeps a fitted image fitted to the window after it's resized to `size`, and any other in
+    /// sight.
+    fn resized(&mut self, size: PhysicalSize<u32>) {
+        if self.view.fitted {
+            self.view.zoom = self.fit.for_image(self.tall, self.image.dimensions()).zoom(
+                self.view.area(size),
+                self.image.dimensions(),
+                self.view.scale,
+            );
+        }
+        self.view.clamp(size, &self.image);
+    }
+
+    /// Does what `key` is bound to, with the modifiers held down now.
+    fn press(
+        &mut self,
+        key: VirtualKeyCode,
+        window: &Window,
+        control_flow: &mut ControlFlow,
+    ) -> Option<Step> {
+        // Whatever it's bound to, Escape closes the help and drops a selection first.
+        if key == VirtualKeyCode::Escape && self.show_help {
+            self.show_help = false;
+            window.request_redraw();
+            return None;
+        }
+        if key == VirtualKeyCode::Escape && self.selection.is_some() {
+            self.selection = None;
+            window.request_redraw();
+            return None;
+        }
+        let action = self.keymap.action(key, self.modifiers)?;
+        self.act(action, window, control_flow)
+    }
+
+    /// Does what `character` is bound to, for keys known by what they type rather than where
+    /// they are on the keyboard.
+    fn type_character(
+        &mut self,
+        character: char,
+        window: &Window,
+        control_flow: &mut ControlFlow,
+    ) -> Option<Step> {
+        let action = self.keymap.character_action(character)?;
+        self.act(action, window, control_flow)
+    }
+
+    /// Does `action`. Closing the window is left to `control_flow`, moving through the playlist
+    /// to the step returned.
+    fn act(
+        &mut self,
+        action: Action,
+        window: &Window,
+        control_flow: &mut ControlFlow,
+    ) -> Option<Step> {
+        let mut step = None;
+        match action {
+            Action::Quit => *control_flow = ControlFlow::Exit,
+            Action::Crop => {
+                let frame_size = self.frames[self.frame_index].buffer.dimensions();
+                let crop = self
+                    .selection
+                    .take()
+                    .and_then(|selection| self.transform.crop_to(selection, frame_size));
+                if crop.is_some() {
+                    self.transform.crop = crop;
+                    self.refresh();
+                    self.refit(window.inner_size());
+                }
+                window.request_redraw();
+            }
+            Action::Filter => {
+                self.filter = self.filter.next();
+                eprintln!("Scaling filter: {:?}", self.filter);
+                window.request_redraw();
+            }
+            Action::Magnify => {
+                self.magnifying = true;
+                window.request_redraw();
+            }
+            Action::Copy => {
+                // With its transparency, rather than flattened onto the backdrop.
+                let copied = apply(&self.frames[self.frame_index].buffer, self.transform);
+                if let Err(err) = copy_to_clipboard(&mut self.clipboard, &copied) {
+                    eprintln!("Unable to copy the image to the clipboard: {}", err);
+                }
+            }
+            Action::SaveFormat => {
+                self.save_format = self.save_format.next();
+                eprintln!("Saving as {:?}", self.save_format);
+            }
+            Action::Save => {
+                let path = self
+                    .playlist
+                    .current()
+                    .edited_path(self.save_format.image_format());
+                let frame = &self.frames[self.frame_index].buffer;
+                let result = if self.save_format.has_alpha() {
+                    save(&apply(frame, self.transform), &path, self.save_format)
+                } else {
+                    let flattened =
+                        prepare(frame, self.transform, Backdrop::Solid(self.background));
+                    save(&flattened, &path, self.save_format)
+                };
+                match result {
+                    Ok(()) => eprintln!("Saved {}", path.display()),
+                    Err(err) => eprintln!("Unable to save {}: {}", path.display(), err),
+                }
+            }
+            Action::Delete => match self.playlist.current() {
+                Source::File(path) => {
+                    if self
+                        .confirm_delete_until
+                        .is_some_and(|until| Instant::now() < until)
+                    {
+                        self.confirm_delete_until = None;
+                        match trash::delete(path) {
+                            Ok(()) => match self.playlist.remove_current() {
+                                Some(_) => {
+                                    self.montage = None;
+                                    step = Some(Step::Stay);
+                                }
+                                None => *control_flow = ControlFlow::Exit,
+                            },
+                            Err(err) => eprintln!("Unable to delete {}: {}", path.display(), err),
+                        }
+                    } else {
+                        self.confirm_delete_until = Some(Instant::now() + DELETE_CONFIRM_TIMEOUT);
+                    }
+                    window.request_redraw();
+                }
+                source => eprintln!("{} isn't a file, so it can't be deleted", source),
+            },
+            Action::Grid if self.config.grid.is_some() && !self.in_grid => {
+                step = Some(Step::Grid);
+            }
+            Action::Edit => match self.playlist.current() {
+                Source::File(path) => {
+                    let editor = self
+                        .config
+                        .editor
+                        .clone()
+                        .or_else(|| env::var("IMG_VWR_EDITOR").ok());
+                    if let Err(err) = open_in_editor(path, editor.as_deref()) {
+                        eprintln!("Unable to open {} in an editor: {}", path.display(), err);
+                    }
+                }
+                source => eprintln!("{} isn't a file, so it can't be edited", source),
+            },
+            Action::PickColor => {
+                let size = window.inner_size();
+                let point = Placement::new(size.width, size.height, &self.image, &self.view)
+                    .image_point(self.cursor);
+                let frame = &self.frames[self.frame_index].buffer;
+                let (x, y) = self.transform.frame_point(point, frame.dimensions());
+                if x >= 0.0 && y >= 0.0 && (x as u32) < frame.width() && (y as u32) < frame.height()
+                {
+                    let (x, y) = (x as u32, y as u32);
+                    let color = describe_pixel(x, y, frame.get_pixel(x, y).0);
+                    println!("{}", color);
+                    self.picked_color = Some(color);
+                    window.request_redraw();
+                }
+            }
+            Action::AlwaysOnTop => {
+                self.always_on_top = !self.always_on_top;
+                window.set_always_on_top(self.always_on_top);
+                window.request_redraw();
+            }
+            action @ (Action::PageUp | Action::PageDown) if self.frames.len() > 1 => {
+                self.frame_index = if action == Action::PageDown {
+                    (self.frame_index + 1).min(self.frames.len() - 1)
+                } else {
+                    self.frame_index.saturating_sub(1)
+                };
+                // Going through an animation frame by frame stops it.
+                if picture::is_animation(&self.frames) {
+                    self.paused = true;
+                }
+                self.refresh();
+                window.request_redraw();
+            }
+            // Paging through a single image that's taller than the window, like a document fit
+            // to the width.
+            action @ (Action::PageUp | Action::PageDown | Action::Top | Action::Bottom) => {
+                let size = window.inner_size();
+                // Keep a little of the last page in sight.
+                let page = (self.view.area(size).height as f32 - PAN_STEP).max(PAN_STEP);
+                let dy = match action {
+                    Action::PageUp => page,
+                    Action::PageDown => -page,
+                    Action::Top => f32::MAX,
+                    _ => -f32::MAX,
+                };
+                if self.animate {
+                    self.view.glide();
+                }
+                self.view.pan(0.0, dy);
+                self.view.clamp(size, &self.image);
+                window.request_redraw();
+            }
+            Action::Compare => {
+                if let Some(comparison) = &mut self.comparison {
+                    comparison.layout = comparison.layout.next();
+                    window.request_redraw();
+                }
+            }
+            Action::Help => {
+                self.show_help = !self.show_help;
+                window.request_redraw();
+            }
+            Action::Info => {
+                self.show_info = !self.show_info;
+                window.request_redraw();
+            }
+            Action::Pause => {
+                self.paused = !self.paused;
+                self.next_frame_at = Instant::now() + self.frames[self.frame_index].delay;
+                self.next_slide_at = self
+                    .config
+                    .slideshow
+                    .map(|interval| Instant::now() + interval);
+            }
+            Action::Fullscreen => match window.fullscreen() {
+                Some(_) => window.set_fullscreen(None),
+                None => window.set_fullscreen(Some(Fullscreen::Borderless(None))),
+            },
+            action @ (Action::ZoomIn | Action::ZoomOut) => {
+                if self.animate {
+                    self.view.glide();
+                }
+                self.view.zoom_by(if action == Action::ZoomIn {
+                    ZOOM_STEP
+                } else {
+                    1.0 / ZOOM_STEP
+                });
+                self.view.clamp(window.inner_size(), &self.image);
+                window.request_redraw();
+            }
+            Action::Reset => {
+                self.transform = self.initial_transform;
+                self.selection = None;
+                self.refresh();
+                self.refit(window.inner_size());
+                window.request_redraw();
+            }
+            Action::FitWindow if window.fullscreen().is_none() => {
+                // Shrink the image back to what fits on the screen if it's zoomed past it.
+                let (image, view) = (&self.image, &self.view);
+                let room = inset(self.max_screen_size, self.padding);
+                let contained = FitMode::Contain.zoom(room, image.dimensions(), view.scale);
+                let exceeds = image.width() as f32 * view.zoom > room.width as f32
+                    || image.height() as f32 * view.zoom > room.height as f32;
+                let zoom = if exceeds { contained } else { view.zoom };
+                let size =
+                    window_size(image.dimensions(), zoom, self.max_screen_size, self.padding);
+                self.view = View {
+                    fitted: view.fitted,
+                    stretch: view.stretch,
+                    ..View::new(zoom, self.padding, view.scale)
+                };
+                window.set_inner_size(size);
+                if let Some(monitor) = window.current_monitor() {
+                    center(window, &monitor, size);
+                }
+                window.request_redraw();
+            }
+            Action::Fit => {
+                self.fit = self.fit.next();
+                // Picking a mode by hand overrides --tall.
+                self.tall = false;
+                self.refit(window.inner_size());
+                window.request_redraw();
+            }
+            Action::Stretch => {
+                self.view.stretch = !self.view.stretch;
+                self.refit(window.inner_size());
+                window.request_redraw();
+            }
+            Action::Histogram => {
+                self.show_histogram = !self.show_histogram;
+                window.request_redraw();
+            }
+            Action::PixelGrid => {
+                self.pixel_grid = !self.pixel_grid;
+                window.request_redraw();
+            }
+            action @ (Action::FlipHorizontal | Action::FlipVertical) => {
+                if action == Action::FlipHorizontal {
+                    self.transform.flip_horizontal = !self.transform.flip_horizontal;
+                } else {
+                    self.transform.flip_vertical = !self.transform.flip_vertical;
+                }
+                self.selection = None;
+                self.refresh();
+                window.request_redraw();
+            }
+            Action::Invert => {
+                self.transform.invert = !self.transform.invert;
+                self.refresh();
+                window.request_redraw();
+            }
+            Action::Grayscale => {
+                self.transform.grayscale = !self.transform.grayscale;
+                self.refresh();
+                window.request_redraw();
+            }
+            action @ (Action::Brighter
+            | Action::Darker
+            | Action::MoreContrast
+            | Action::LessContrast) => {
+                let sign = if matches!(action, Action::Brighter | Action::MoreContrast) {
+                    1.0
+                } else {
+                    -1.0
+                };
+                let transform = &mut self.transform;
+                if matches!(action, Action::MoreContrast | Action::LessContrast) {
+                    transform.contrast =
+                        (transform.contrast + sign * CONTRAST_STEP).clamp(0.0, MAX_CONTRAST);
+                } else {
+                    transform.brightness =
+                        (transform.brightness + sign * BRIGHTNESS_STEP).clamp(-1.0, 1.0);
+                }
+                self.refresh();
+                window.request_redraw();
+            }
+            action @ (Action::RotateClockwise | Action::RotateCounterclockwise) => {
+                self.transform.rotation = if action == Action::RotateCounterclockwise {
+                    (self.transform.rotation + 3) % 4
+                } else {
+                    (self.transform.rotation + 1) % 4
+                };
+                self.selection = None;
+                self.refresh();
+                self.refit(window.inner_size());
+                window.request_redraw();
+            }
+            // The arrow keys flip through the playlist unless there is something to pan to.
+            action @ (Action::Next | Action::Prev | Action::Left | Action::Right)
+                if matches!(action, Action::Next | Action::Prev)
+                    || self
+                        .view
+                        .fits_horizontally(window.inner_size(), &self.image) =>
+            {
+                step = match action {
+                    Action::Next | Action::Right => Some(Step::Next),
+                    _ => Some(Step::Prev),
+                };
+            }
+            action @ (Action::Left | Action::Right | Action::Up | Action::Down) => {
+                let (dx, dy) = match action {
+                    Action::Left => (PAN_STEP, 0.0),
+                    Action::Right => (-PAN_STEP, 0.0),
+                    Action::Up => (0.0, PAN_STEP),
+                    _ => (0.0, -PAN_STEP),
+                };
+                if self.animate {
+                    self.view.glide();
+                }
+                self.view.pan(dx, dy);
+                self.view.clamp(window.inner_size(), &self.image);
+                window.request_redraw();
+            }
+            _ => {}
+        }
+        step
+    }
+
+    /// Starts dragging the image, the divider of a comparison or the view on the minimap when the
+    /// left button goes down. Letting go without having moved opens the image clicked in the grid.
+    fn click(&mut self, state: ElementState, window: &Window) -> Option<Step> {
+        let size = window.inner_size();
+        let cursor = self.cursor;
+        let pressed = state == ElementState::Pressed;
+        let clicked = !pressed
+            && !self.dragging_minimap
+            && (cursor.x - self.pressed_at.x).hypot(cursor.y - self.pressed_at.y) <= CLICK_SLOP;
+        let on_minimap =
+            minimap_point(self.minimap.as_ref(), &self.view, &self.image, size, cursor)
+                .filter(|_| pressed);
+        self.dragging_minimap = on_minimap.is_some();
+        self.dragging_divider = pressed
+            && !self.dragging_minimap
+            && self
+                .comparison
+                .as_ref()
+                .is_some_and(|comparison| comparison.grabs(cursor.x, size.width));
+        self.dragging = pressed && !self.dragging_minimap && !self.dragging_divider;
+        if let Some(point) = on_minimap {
+            if self.animate {
+                self.view.glide();
+            }
+            self.view.center_on(point, size, &self.image);
+            window.request_redraw();
+        }
+        if pressed {
+            self.pressed_at = cursor;
+        } else if clicked && self.in_grid && self.transform.keeps_geometry() {
+            let (x, y) = Placement::new(size.width, size.height, &self.image, &self.view)
+                .image_point(cursor);
+            let cell = self
+                .montage
+                .as_ref()
+                .and_then(|montage| montage.cell_at(x, y));
+            if let Some(index) = cell {
+                self.playlist.go_to(index);
+                return Some(Step::Stay);
+            }
+        }
+        None
+    }
+
+    /// Starts the selection at the cursor when the right button goes down, and ends it there
+    /// when it's let go of.
+    fn select(&mut self, state: ElementState, window: &Window) {
+        let size = window.inner_size();
+        let point = Placement::new(size.width, size.height, &self.image, &self.view)
+            .image_point(self.cursor);
+        self.selecting = state == ElementState::Pressed;
+        if self.selecting {
+            self.selection = Some((point, point));
+        } else if let Some((start, _)) = self.selection {
+            self.selection = Some((start, point));
+        }
+        window.request_redraw();
+    }
+
+    /// Drags whatever is being dragged along to the cursor at `position`.
+    fn move_cursor(&mut self, position: PhysicalPosition<f64>, window: &Window) {
+        let size = window.inner_size();
+        if let Some(comparison) = &mut self.comparison {
+            if self.dragging_divider {
+                comparison.drag_to(position.x, size.width);
+                window.request_redraw();
+            }
+            window.set_cursor_icon(
+                if self.dragging_divider || comparison.grabs(position.x, size.width) {
+                    CursorIcon::ColResize
+                } else {
+                    CursorIcon::Default
+                },
+            );
+        }
+        if self.dragging_minimap {
+            let point = minimap_point(
+                self.minimap.as_ref(),
+                &self.view,
+                &self.image,
+                size,
+                position,
+            );
+            if let Some(point) = point {
+                self.view.center_on(point, size, &self.image);
+                window.request_redraw();
+            }
+        }
+        if self.dragging {
+            self.view.pan(
+                (position.x - self.cursor.x) as f32,
+                (position.y - self.cursor.y) as f32,
+            );
+            self.view.clamp(size, &self.image);
+            window.request_redraw();
+        }
+        if self.magnifying {
+            window.request_redraw();
+        }
+        self.cursor = position;
+        if let Some((start, _)) = self.selection.filter(|_| self.selecting) {
+            let point = Placement::new(size.width, size.height, &self.image, &self.view)
+                .image_point(position);
+            self.selection = Some((start, point));
+            window.request_redraw();
+        }
+    }
+
+    /// Zooms in or out around the cursor as the wheel turns, or scrolls an image fit to the width.
+    fn scroll(&mut self, delta: MouseScrollDelta, window: &Window) {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_SCROLL_LINE,
+        };
+        let size = window.inner_size();
+        // Fit to the width, there's only up and down to go, so the wheel scrolls there. Ctrl
+        // still zooms.
+        let scrolls = self.fit.for_image(self.tall, self.image.dimensions()) == FitMode::Width;
+        if self.animate {
+            self.view.glide();
+        }
+        if scrolls && !self.modifiers.ctrl() {
+            self.view.pan(0.0, lines * PAN_STEP);
+        } else {
+            let anchor = (
+                self.cursor.x as f32 - size.width as f32 / 2.0,
+                self.cursor.y as f32 - size.height as f32 / 2.0,
+            );
+            self.view.zoom_at(ZOOM_STEP.powf(lines), anchor);
+        }
+        self.view.clamp(size, &self.image);
+        window.request_redraw();
+    }
+
+    /// Keeps the image looking as big as it did on the other monitor, after the window moved to
+    /// one with a `scale` factor, which resized it to `size`.
+    fn rescale(&mut self, scale: f32, size: PhysicalSize<u32>) {
+        if self.view.fitted {
+            self.view.scale = scale;
+            self.refit(size);
+        } else {
+            let factor = scale / self.view.scale;
+            self.view.scale = scale;
+            self.view.zoom_by(factor);
+            self.view.clamp(size, &self.image);
+        }
+    }
+
+    /// Brings the title up to date and draws the window.
+    fn repaint(&mut self, window: &Window, pixels: &mut Pixels) {
+        let mut new_title = if self.in_grid {
+            grid_title(&self.playlist)
+        } else if let Some(template) = &self.config.title {
+            expand_title(
+                template,
+                &self.playlist,
+                self.image.dimensions(),
+                self.view.perceived_zoom(),
+            )
+        } else {
+            title(
+                &self.playlist,
+                self.transform.rotation,
+                self.load_error.as_deref(),
+            )
+        };
+        if let Some(comparison) = &self.comparison {
+            new_title.push_str(&format!(" vs {}", comparison.source));
+            match comparison.layout {
+                Layout::Split => {}
+                Layout::First => new_title.push_str(" — showing the first"),
+                Layout::Second => new_title.push_str(" — showing the second"),
+            }
+        }
+        if self.always_on_top {
+            new_title.push_str(" — on top");
+        }
+        if new_title != self.window_title {
+            window.set_title(&new_title);
+            self.window_title = new_title;
+        }
+
+        let now = Instant::now();
+        self.last_redraw = now;
+        let shown = self.view.shown(now);
+        self.view.settle(now);
+        let extent = pixels.context().texture_extent;
+        let size = PhysicalSize::new(extent.width, extent.height);
+        let status = self.status();
+        let show_minimap = !shown.fits(size, &self.image);
+        if show_minimap {
+            self.minimap
+                .get_or_insert_with(|| Minimap::new(&self.image));
+        }
+        if self.show_histogram {
+            // Counted before transparency is flattened onto the backdrop, which would add its
+            // own colors.
+            self.histogram.get_or_insert_with(|| {
+                Histogram::new(&apply(
+                    &self.frames[self.frame_index].buffer,
+                    self.transform,
+                ))
+            });
+        }
+        let scene = Scene {
+            image: &self.image,
+            view: shown,
+            background: self.background,
+            filter: self.filter,
+            pixel_grid: self.pixel_grid,
+            comparison: self.comparison.as_ref(),
+            status: status.as_deref(),
+            selection: self.selection,
+            minimap: self.minimap.as_ref().filter(|_| show_minimap),
+            histogram: self.histogram.as_ref().filter(|_| self.show_histogram),
+            magnifier: self.magnifying.then_some(self.cursor),
+            help: self.show_help.then(|| self.keymap.help()),
+            drop_hint: self.hovering_file,
+        };
+        if let Err(err) = redraw(pixels, &scene) {
+            log::warn!("Unable to draw the window: {}", err);
+        }
+    }
+
+    /// What the status bar says, if it's up.
+    fn status(&self) -> Option<String> {
+        let playlist = &self.playlist;
+        if self.loading {
+            Some(format!("Loading {}...", playlist.current()))
+        } else if self.confirm_delete_until.is_some() {
+            Some(format!(
+                "Press Delete again to move {} to the trash",
+                playlist.current()
+            ))
+        } else if self.end_notice_until.is_some() {
+            Some(end_notice(playlist).to_string())
+        // A failed image has nothing else to show, so its error is always spelled out.
+        } else if let Some(error) = &self.load_error {
+            Some(error.clone())
+        } else if self.show_info {
+            let mut info = position(playlist);
+            // Without --loop, stepping stops at the ends.
+            if !self.config.looping && playlist.len() > 1 {
+                if playlist.is_first() {
+                    info.push_str(" first");
+                } else if playlist.is_last() {
+                    info.push_str(" last");
+                }
+            }
+            info.push_str(&format!(
+                "  {}  {}x{}  {:.0}%",
+                playlist.current(),
+                self.image.width(),
+                self.image.height(),
+                self.view.perceived_zoom() * 100.0
+            ));
+            if self.frames.len() > 1 && !picture::is_animation(&self.frames) {
+                info.push_str(&format!(
+                    "  page {}/{}",
+                    self.frame_index + 1,
+                    self.frames.len()
+                ));
+            }
+            // Images without a profile are sRGB too, other profiles explain why the colors may
+            // look off, since they're displayed as if they were sRGB.
+            let profile = self.color_profile.as_ref();
+            if let Some(name) = profile.filter(|name| !icc::is_srgb(name)) {
+                info.push_str(&format!("  {} profile", name));
+            }
+            if self.view.stretch {
+                info.push_str("  stretched");
+            }
+            if self.transform.invert {
+                info.push_str("  inverted");
+            }
+            if self.transform.grayscale {
+                info.push_str("  gray");
+            }
+            if let Some(color) = &self.picked_color {
+                info.push_str("  ");
+                info.push_str(color);
+            }
+            Some(info)
+        } else {
+            None
+        }
+    }
+
+    /// When the event loop has to wake up next for something to move on, if anything does.
+    fn deadline(&self) -> Option<Instant> {
+        // Nothing moving is drawn more often than --max-fps allows.
+        let next_redraw = self.last_redraw + self.frame_interval;
+        let animation =
+            picture::is_animation(&self.frames).then_some(self.next_frame_at.max(next_redraw));
+        let glide_frame = self.view.glide.map(|_| next_redraw);
+        [animation, self.next_slide_at]
+            .into_iter()
+            .filter(|_| !self.paused)
+            .chain([
+                self.confirm_delete_until,
+                self.end_notice_until,
+                glide_frame,
+            ])
+            .flatten()
+            .min()
+    }
+
+    /// Takes `step` through the playlist and shows the image it lands on, once it's decoded.
+    fn go(
+        &mut self,
+        step: Step,
+        window: &Window,
+        preloader: &mut Preloader,
+        control_flow: &mut ControlFlow,
+    ) {
+        // Without --loop, stepping past either end stays put, or quits past the last image with
+        // --on-end quit.
+        let past_end = match step {
+            Step::Next => self.playlist.is_last(),
+            Step::Prev => self.playlist.is_first(),
+            _ => false,
+        };
+        if past_end && !self.config.looping {
+            if matches!(step, Step::Next) && self.config.on_end == OnEnd::Quit {
+                *control_flow = ControlFlow::Exit;
+            } else {
+                self.end_notice_until = Some(Instant::now() + END_NOTICE_TIME);
+                window.request_redraw();
+            }
+            // A slideshow ends with the last image.
+            self.next_slide_at = None;
+            return;
+        }
+
+        let loaded = match (step, self.config.grid) {
+            (Step::Grid, Some(columns)) => {
+                let montage = self.montage.get_or_insert_with(|| {
+                    build_montage(
+                        &self.playlist,
+                        columns,
+                        self.autorotate,
+                        self.background,
+                        self.backdrop,
+                    )
+                });
+                Some((vec![montage.frame()], None, None))
+            }
+            _ => {
+                let source = match step {
+                    Step::Next => self.playlist.next(),
+                    Step::Prev => self.playlist.prev(),
+                    Step::Stay | Step::Grid => self.playlist.current(),
+                };
+                let loaded = preloader.take(source).map(|result| match result {
+                    Ok(loaded) => (loaded.frames, None, loaded.color_profile),
+                    Err(err) => {
+                        let (frames, error) = with_placeholder(source, Err(err));
+                        (frames, error, None)
+                    }
+                });
+                if loaded.is_some() {
+                    preloader.prefetch(&self.playlist.neighbors());
+                } else {
+                    preloader.prefetch(&with_current(&self.playlist));
+                }
+                loaded
+            }
+        };
+
+        // Until it's decoded, the previous image stays up and the slideshow waits.
+        self.loading = loaded.is_none();
+        if self.loading {
+            self.next_slide_at = None;
+        }
+        if let Some((frames, error, color_profile)) = loaded {
+            self.in_grid = matches!(step, Step::Grid);
+            self.frames = frames;
+            self.color_profile = color_profile;
+            self.load_error = error;
+            self.vector = !self.in_grid && svg::is_svg(self.playlist.current());
+            self.frame_index = 0;
+            self.next_frame_at = Instant::now() + self.frames[0].delay;
+            // Stepping by hand restarts the countdown to the next slide.
+            self.next_slide_at = self
+                .config
+                .slideshow
+                .map(|interval| Instant::now() + interval);
+            self.confirm_delete_until = None;
+            self.picked_color = None;
+            self.transform = self.initial_transform;
+            self.selection = None;
+            self.raster_scale = 1.0;
+            self.refresh();
+            let image_size = self.image.dimensions();
+            if let Some(expected_size) = self.initial_size.take() {
+                if image_size != expected_size && window.fullscreen().is_none() {
+                    let zoom = match self.config.zoom {
+                        Some(zoom) => (zoom * self.view.scale).clamp(MIN_ZOOM, MAX_ZOOM),
+                        None => window_zoom(
+                            self.fit,
+                            self.tall,
+                            self.max_screen_size,
+                            image_size,
+                            self.padding,
+                            self.view.scale,
+                        ),
+                    };
+                    let size = window_size(image_size, zoom, self.max_screen_size, self.padding);
+                    window.set_inner_size(size);
+                    if let Some(monitor) =
+                        window.current_monitor().filter(|_| !self.config.no_center)
+                    {
+                        center(window, &monitor, size);
+                    }
+                }
+            }
+            self.refit(window.inner_size());
+            if mem::take(&mut self.start_pending) {
+                let (zoom, pan) = (self.config.zoom, self.config.pan);
+                self.view
+                    .start_at(zoom, pan, window.inner_size(), &self.image);
+            }
+        }
+        window.request_redraw();
+    }
+
+    /// Rasterizes a vector image again whenever it's zoomed past the resolution it was rasterized
+    /// at, or well back out of it, so that it stays sharp. Not when it's compared to another
+    /// image, which it would no longer line up with.
+    fn rerasterize(&mut self, window: &Window) {
+        if !self.vector || self.loading || self.load_error.is_some() || self.comparison.is_some() {
+            return;
+        }
+        let raster_scale = self.raster_scale;
+        let declared = |side: u32| ((side as f32 / raster_scale).round() as u32).max(1);
+        let declared_image = (declared(self.image.width()), declared(self.image.height()));
+        // The zoom in pixels of the size the image declares.
+        let zoom = if self.view.fitted {
+            self.fit.for_image(self.tall, declared_image).zoom(
+                self.view.area(window.inner_size()),
+                declared_image,
+                self.view.scale,
+            )
+        } else {
+            self.view.zoom * raster_scale
+        };
+        let frame_size = self.frames[0].buffer.dimensions();
+        let wanted = svg::raster_scale(zoom, (declared(frame_size.0), declared(frame_size.1)));
+        if wanted != raster_scale {
+            let started = Instant::now();
+            let rasterized = svg::load(self.playlist.current(), wanted);
+            log::debug!("Rasterized at {}x in {:?}", wanted, started.elapsed());
+            match rasterized {
+                Ok(rasterized) => {
+                    let ratio = wanted / raster_scale;
+                    let scale_point = |(x, y): (f32, f32)| (x * ratio, y * ratio);
+                    self.raster_scale = wanted;
+                    self.frames = rasterized;
+                    self.frame_index = 0;
+                    self.transform.crop = self.transform.crop.map(|crop| crop.scaled(ratio));
+                    self.selection = self
+                        .selection
+                        .map(|(a, b)| (scale_point(a), scale_point(b)));
+                    self.refresh();
+                    // It stays the same size on screen, and there's no gliding from the zoom at
+                    // the old resolution.
+                    self.view.zoom = zoom / wanted;
+                    self.view.glide = None;
+                    self.view.clamp(window.inner_size(), &self.image);
+                }
+                Err(err) => self.load_error = Some(err.to_string()),
+            }
+            window.request_redraw();
+        } else if self.view.fitted && self.view.zoom != zoom / raster_scale {
+            // Refitting went by the rasterized size rather than the declared one.
+            self.view.zoom = zoom / raster_scale;
+            self.view.clamp(window.inner_size(), &self.image);
+            window.request_redraw();
+        }
+    }
+}
+
+/// How big the window may get on a screen of `screen_size`.
+fn max_window_size(config: &Config, screen_size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    let percent = config.screen_percent.unwrap_or(SCREEN_PERCENT);
+    config.max_window.unwrap_or_else(|| {
+        PhysicalSize::new(
+            (screen_size.width * percent / 100).max(1),
+            (screen_size.height * percent / 100).max(1),
+        )
+    })
+}
+
+/// Renders the image from `source` the way a freshly opened window would show it, and saves that
+/// to `out`.
+fn screenshot(
+    source: &Source,
+    out: &Path,
+    config: &Config,
+    transform: Transform,
+    backdrop: Backdrop,
+) -> Result<()> {
+    let frames = picture::load(source, !config.no_autorotate).map_err(|err| err.in_file(source))?;
+    let image = prepare(&frames[0].buffer, transform, backdrop);
+    let fit = config.fit.unwrap_or_default();
+    let max_size = max_window_size(config, ASSUMED_SCREEN_SIZE);
+    let zoom = match config.zoom {
+        Some(zoom) => zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+        None => window_zoom(
+            fit,
+            config.tall,
+            max_size,
+            image.dimensions(),
+            config.padding,
+            1.0,
+        ),
+    };
+    let size = window_size(image.dimensions(), zoom, max_size, config.padding);
+    // Refit to the window like after it's opened.
+    let mut view = View {
+        stretch: config.stretch,
+        ..View::new(1.0, config.padding, 1.0)
+    };
+    view.refit(fit.for_image(config.tall, image.dimensions()), size, &image);
+    view.start_at(config.zoom, config.pan, size, &image);
+
+    let mut frame = vec![0; size.width as usize * size.height as usize * 4];
+    draw(
+        &mut frame,
+        size.width,
+        size.height,
+        &image,
+        &view,
+        config.background.unwrap_or_default(),
+        config.filter.unwrap_or_default(),
+    );
+    image::save_buffer(out, &frame, size.width, size.height, ColorType::Rgba8)
+        .map_err(|err| RvuError::from(err).in_file(out.display()))
+}
+
+/// The zoom a window is sized for to show an image of `image_size` in mode `fit` inside
+/// `padding`, when it may be up to `max_size` on a screen of `scale`.
+fn window_zoom(
+    fit: FitMode,
+    tall: bool,
+    max_size: PhysicalSize<u32>,
+    image_size: (u32, u32),
+    padding: u32,
+    scale: f32,
+) -> f32 {
+    let image_fit = fit.for_image(tall, image_size);
+    let zoom = image_fit.zoom(inset(max_size, padding), image_size, scale);
+    // A strip that's scrolled through isn't blown up to the width of the screen.
+    if image_fit != fit {
+        zoom.min(scale)
     } else {
-        ((current_size as f32) / (max_size as f32)).ceil() as u32
+        zoom
     }
 }
 
+/// Parses a clockwise rotation in degrees into quarter turns.
+fn parse_rotation(s: &str) -> std::result::Result<u8, String> {
+    match s.parse::<i32>() {
+        Ok(degrees) if degrees % 90 == 0 => Ok(degrees.rem_euclid(360).div_euclid(90) as u8),
+        _ => Err(format!("`{}` is not a multiple of 90 degrees", s)),
+    }
+}
+
+/// The current image of `playlist` followed by its neighbors, for decoding in that order.
+fn with_current(playlist: &Playlist) -> Vec<&Source> {
+    let mut sources = vec![playlist.current()];
+    sources.extend(playlist.neighbors());
+    sources
+}
+
+/// Decodes every image of `playlist` into a contact sheet `columns` thumbnails wide. Images that
+/// fail to load get a thumbnail of the usual placeholder.
+fn build_montage(
+    playlist: &Playlist,
+    columns: u32,
+    autorotate: bool,
+    background: Color,
+    backdrop: Backdrop,
+) -> Montage {
+    let thumbnails: Vec<_> = playlist
+        .sources()
+        .iter()
+        .map(|source| {
+            let (frames, _) = load_or_placeholder(source, autorotate);
+            let thumbnail = montage::thumbnail(&frames[0].buffer);
+            prepare(&thumbnail, Transform::default(), backdrop)
+        })
+        .collect();
+    Montage::new(&thumbnails, columns, background.to_rgba())
+}
+
+/// The playlist for a file or directory dropped onto the window: like one given on the command
+/// line, except that problems listing a directory still leave the file itself to show its error.
+fn open_dropped(path: PathBuf, recursive: bool, order: SortOrder) -> Playlist {
+    if path.is_dir() {
+        if let Ok(paths) = playlist::scan_dir(&path, recursive, order) {
+            if !paths.is_empty() {
+                return Playlist::new(paths.into_iter().map(Source::File).collect());
+            }
+        }
+    } else if archive::is_archive(&path) {
+        if let Ok(images) = archive::images(&path) {
+            if !images.is_empty() {
+                return Playlist::new(images);
+            }
+        }
+    } else if let Ok(playlist) = Playlist::from_siblings(&path, order) {
+        return playlist;
+    }
+    Playlist::new(vec![Source::File(path)])
+}
+
+/// Starts `editor`, a program possibly followed by arguments, on `path` without waiting for it.
+/// Without an editor, the file goes to whatever the system opens images with.
+fn open_in_editor(path: &Path, editor: Option<&str>) -> io::Result<()> {
+    let mut words = editor.unwrap_or_default().split_whitespace();
+    let mut command = match words.next() {
+        Some(program) => {
+            let mut command = Command::new(program);
+            command.args(words);
+            command
+        }
+        None => default_opener(),
+    };
+    let mut child = command.arg(path).spawn()?;
+    // Reap it once it's done, so it doesn't linger as a zombie.
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn default_opener() -> Command {
+    Command::new("open")
+}
+
+#[cfg(windows)]
+fn default_opener() -> Command {
+    // `start` is built into the shell, and takes its first quoted argument as a window title.
+    let mut command = Command::new("cmd");
+    command.args(["/C", "start", ""]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn default_opener() -> Command {
+    Command::new("xdg-open")
+}
+
+/// Describes the color of the pixel at (`x`, `y`) as both RGBA and hex, e.g.
+/// `(12, 34) rgba(255, 0, 0, 255) #ff0000ff`.
+fn describe_pixel(x: u32, y: u32, [r, g, b, a]: [u8; 4]) -> String {
+    format!(
+        "({}, {}) rgba({}, {}, {}, {}) #{:02x}{:02x}{:02x}{:02x}",
+        x, y, r, g, b, a, r, g, b, a
+    )
+}
+
+/// Puts `image` on the system clipboard, opening `clipboard` first if it isn't already.
+fn copy_to_clipboard(
+    clipboard: &mut Option<Clipboard>,
+    image: &RgbaImage,
+) -> std::result::Result<(), arboard::Error> {
+    let clipboard = match clipboard {
+        Some(clipboard) => clipboard,
+        None => clipboard.insert(Clipboard::new()?),
+    };
+    clipboard.set_image(ImageData {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        bytes: Cow::Borrowed(image.as_raw()),
+    })
+}
+
+/// Parses a size in pixels written as `WIDTHxHEIGHT`, neither of them zero.
+fn parse_size(s: &str) -> std::result::Result<PhysicalSize<u32>, String> {
+    let invalid = || format!("`{}` is not a size like 1280x720", s);
+    let (width, height) = s.split_once(['x', 'X']).ok_or_else(invalid)?;
+    match (width.trim().parse::<u32>(), height.trim().parse::<u32>()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok(PhysicalSize::new(width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a percentage from 1 to 100.
+fn parse_percent(s: &str) -> std::result::Result<u32, String> {
+    match s.trim_end_matches('%').parse::<u32>() {
+        Ok(percent) if (1..=100).contains(&percent) => Ok(percent),
+        _ => Err(format!("`{}` is not a percentage from 1 to 100", s)),
+    }
+}
+
+/// Parses a margin of up to MAX_PADDING pixels.
+fn parse_padding(s: &str) -> std::result::Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(padding) if padding <= MAX_PADDING => Ok(padding),
+        _ => Err(format!(
+            "`{}` is not a number of pixels from 0 to {}",
+            s, MAX_PADDING
+        )),
+    }
+}
+
+/// Parses a number of grid columns, at least one.
+fn parse_columns(s: &str) -> std::result::Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(columns) if columns > 0 => Ok(columns),
+        _ => Err(format!("`{}` is not a positive number of columns", s)),
+    }
+}
+
+/// Parses a positive zoom factor, fractions allowed.
+fn parse_zoom(s: &str) -> std::result::Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(zoom) if zoom > 0.0 && zoom.is_finite() => Ok(zoom),
+        _ => Err(format!("`{}` is not a positive zoom factor", s)),
+    }
+}
+
+/// Parses a pan offset in pixels written as `X,Y`.
+fn parse_pan(s: &str) -> std::result::Result<(f32, f32), String> {
+    let invalid = || format!("`{}` is not a pan offset like 200,-100", s);
+    let (x, y) = s.split_once(',').ok_or_else(invalid)?;
+    match (x.trim().parse::<f32>(), y.trim().parse::<f32>()) {
+        (Ok(x), Ok(y)) if x.is_finite() && y.is_finite() => Ok((x, y)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a positive whole number of frames per second.
+fn parse_fps(s: &str) -> std::result::Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(fps) if fps > 0 => Ok(fps),
+        _ => Err(format!(
+            "`{}` is not a positive number of frames per second",
+            s
+        )),
+    }
+}
+
+/// Parses a positive number of seconds, fractions allowed.
+fn parse_seconds(s: &str) -> std::result::Result<Duration, String> {
+    match s.parse::<f32>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(Duration::from_secs_f32(seconds)),
+        _ => Err(format!("`{}` is not a positive number of seconds", s)),
+    }
+}
+
+/// Writes `image` to `path` in `format`.
+fn save(image: &RgbaImage, path: &Path, format: SaveFormat) -> image::ImageResult<()> {
+    image::save_buffer_with_format(
+        path,
+        image.as_raw(),
+        image.width(),
+        image.height(),
+        ColorType::Rgba8,
+        format.image_format(),
+    )
+}
+
+/// Loads the image from `source`. A failure is reported on stderr and replaced by a placeholder,
+/// so that the window still shows up and the rest of the playlist stays reachable.
+fn load_or_placeholder(source: &Source, autorotate: bool) -> (Vec<Frame>, Option<String>) {
+    with_placeholder(
+        source,
+        picture::load(source, autorotate).map_err(|err| err.to_string()),
+    )
+}
+
+/// Swaps the error of loading `source` for the placeholder, reporting it along the way.
+fn with_placeholder(
+    source: &Source,
+    result: std::result::Result<Vec<Frame>, String>,
+) -> (Vec<Frame>, Option<String>) {
+    match result {
+        Ok(frames) => (frames, None),
+        Err(err) => {
+            eprintln!("{}: {}", source, err);
+            (picture::placeholder(), Some(err))
+        }
+    }
+}
+
+/// The window title for the current image of `playlist`: its name, how many quarter turns
+/// `rotation` it's turned by, where it is in the playlist and the reason it couldn't be shown.
+fn title(playlist: &Playlist, rotation: u8, error: Option<&str>) -> String {
+    let mut title = playlist.current().to_string();
+    if rotation != 0 {
+        title.push_str(&format!(" ({}°)", u32::from(rotation) * 90));
+    }
+    if playlist.len() > 1 {
+        title.push_str(&format!(" — {}", position(playlist)));
+    }
+    if let Some(error) = error {
+        title.push_str(&format!(" — {}", error));
+    }
+    title
+}
+
+/// Fills in the tokens of a `--title` template for the current image of `playlist`, displayed
+/// `size` pixels big at `zoom`. Anything in braces that isn't a token is kept as it is.
+fn expand_title(template: &str, playlist: &Playlist, size: (u32, u32), zoom: f32) -> String {
+    let path = playlist.current().to_string();
+    let mut title = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        title.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let value = match &rest[1..end] {
+            "name" => Path::new(&path)
+                .file_name()
+                .map_or_else(|| path.clone(), |name| name.to_string_lossy().into_owned()),
+            "path" => path.clone(),
+            "w" => size.0.to_string(),
+            "h" => size.1.to_string(),
+            "zoom" => format!("{:.0}", zoom * 100.0),
+            "pos" => position(playlist),
+            "index" => (playlist.index() + 1).to_string(),
+            "count" => playlist.len().to_string(),
+            _ => rest[..=end].to_string(),
+        };
+        title.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    title.push_str(rest);
+    title
+}
+
+/// The window title while the contact sheet is shown.
+fn grid_title(playlist: &Playlist) -> String {
+    format!("{} images", playlist.len())
+}
+
+/// Where the current image is in `playlist`, such as `3/57`.
+fn position(playlist: &Playlist) -> String {
+    format!("{}/{}", playlist.index() + 1, playlist.len())
+}
+
+/// What the status bar says when stepping past the current image leads nowhere.
+fn end_notice(playlist: &Playlist) -> &'static str {
+    if playlist.len() == 1 {
+        "This is the only image"
+    } else if playlist.is_first() {
+        "This is the first image"
+    } else {
+        "This is the last image"
+    }
+}
+
+/// Moves `window` to the middle of `monitor`, assuming it's about to get an inner size of
+/// `inner_size`.
+fn center(window: &Window, monitor: &MonitorHandle, inner_size: PhysicalSize<u32>) {
+    // The decorations keep their size whatever the inner size is.
+    let outer = window.outer_size();
+    let inner = window.inner_size();
+    let width = inner_size.width + outer.width.saturating_sub(inner.width);
+    let height = inner_size.height + outer.height.saturating_sub(inner.height);
+
+    let origin = monitor.position();
+    let size = monitor.size();
+    window.set_outer_position(PhysicalPosition::new(
+        origin.x + (i64::from(size.width) - i64::from(width)).max(0) as i32 / 2,
+        origin.y + (i64::from(size.height) - i64::from(height)).max(0) as i32 / 2,
+    ));
+}
+
+/// Whether the window corner at `position` is on one of the monitors.
+fn is_on_screen<T>(event_loop: &EventLoop<T>, position: PhysicalPosition<i32>) -> bool {
+    event_loop.available_monitors().any(|monitor| {
+        let origin = monitor.position();
+        let size = monitor.size();
+        (origin.x..origin.x + size.width as i32).contains(&position.x)
+            && (origin.y..origin.y + size.height as i32).contains(&position.y)
+    })
+}
+
+/// Resizes both the surface and the pixel buffer so that one buffer pixel is one window pixel.
 fn resize(pixels: &mut Pixels, size: &PhysicalSize<u32>) {
+    if size.width == 0 || size.height == 0 {
+        return;
+    }
     pixels.resize_surface(size.width, size.height);
+    pixels.resize_buffer(size.width, size.height);
+}
+
+/// The pixel of `image` under `cursor` on `minimap`, if it's shown, which it is while the image
+/// doesn't fit in the window.
+fn minimap_point(
+    minimap: Option<&Minimap>,
+    view: &View,
+    image: &RgbaImage,
+    size: PhysicalSize<u32>,
+    cursor: PhysicalPosition<f64>,
+) -> Option<(f32, f32)> {
+    let minimap = minimap.filter(|_| !view.fits(size, image))?;
+    let text_scale = overlay::text_scale(size.width);
+    minimap.image_point(size.width, size.height, text_scale, cursor.x, cursor.y)
+}
+
+/// Everything the window shows, for a redraw.
+struct Scene<'a> {
+    image: &'a RgbaImage,
+    /// The view as it's shown right now, part of the way there while gliding.
+    view: View,
+    background: Color,
+    filter: Filter,
+    /// Whether lines are drawn between the pixels when they're zoomed in far enough.
+    pixel_grid: bool,
+    comparison: Option<&'a Comparison>,
+    /// What the status bar says, if it's up.
+    status: Option<&'a str>,
+    selection: Option<((f32, f32), (f32, f32))>,
+    minimap: Option<&'a Minimap>,
+    histogram: Option<&'a Histogram>,
+    /// Where the cursor is, while the pixels around it are magnified.
+    magnifier: Option<PhysicalPosition<f64>>,
+    /// The lines of the help, while it's shown.
+    help: Option<Vec<String>>,
+    /// Whether a file is being dragged over the window.
+    drop_hint: bool,
+}
+
+/// Draws the image of `scene` into the buffer of `pixels`, puts the overlays on top and shows
+/// the result.
+fn redraw(pixels: &mut Pixels, scene: &Scene) -> std::result::Result<(), pixels::Error> {
+    let started = Instant::now();
+    let extent = pixels.context().texture_extent;
+    let (width, height) = (extent.width, extent.height);
+    let view = &scene.view;
+    let image = scene.image;
+    let drawn = match scene.comparison {
+        Some(comparison) if comparison.layout == Layout::Second => &comparison.image,
+        _ => image,
+    };
+    draw(
+        pixels.get_frame(),
+        width,
+        height,
+        drawn,
+        view,
+        scene.background,
+        scene.filter,
+    );
+    let divider = scene
+        .comparison
+        .filter(|comparison| comparison.layout == Layout::Split)
+        .map(|comparison| {
+            let x = comparison.divider_x(width);
+            draw_columns(
+                pixels.get_frame(),
+                (width, height),
+                x..width,
+                &comparison.image,
+                view,
+                scene.background,
+                scene.filter,
+            );
+            x
+        });
+    if scene.pixel_grid {
+        let frame = pixels.get_frame();
+        let first = 0..divider.unwrap_or(width);
+        draw_pixel_grid(frame, (width, height), first, drawn, view);
+        if let (Some(x), Some(comparison)) = (divider, scene.comparison) {
+            draw_pixel_grid(frame, (width, height), x..width, &comparison.image, view);
+        }
+    }
+    log::debug!(
+        "Drew {}x{} at {:.0}% in {:?}",
+        width,
+        height,
+        view.zoom * 100.0,
+        started.elapsed()
+    );
+
+    let mut canvas = Canvas::new(pixels.get_frame(), width, height);
+    let text_scale = overlay::text_scale(width);
+    if let Some(status) = scene.status {
+        canvas.status_bar(status, text_scale);
+    }
+    let placement = Placement::new(width, height, image, view);
+    if let Some((start, end)) = scene.selection {
+        let (left, top) = placement.window_point(start.0.min(end.0), start.1.min(end.1));
+        let (right, bottom) = placement.window_point(start.0.max(end.0), start.1.max(end.1));
+        let (width, height) = ((right - left) as u32, (bottom - top) as u32);
+        canvas.blend_rect(left, top, width, height, SELECTION_COLOR, SELECTION_ALPHA);
+        canvas.outline(left, top, width, height, 1, SELECTION_COLOR);
+    }
+    if let Some(minimap) = scene.minimap {
+        let area = view.area(PhysicalSize::new(width, height));
+        let margin = (
+            f64::from((width - area.width) / 2),
+            f64::from((height - area.height) / 2),
+        );
+        let corner = |x: f64, y: f64| {
+            let (x, y) = placement.image_point(PhysicalPosition::new(x, y));
+            (
+                x.clamp(0.0, image.width() as f32),
+                y.clamp(0.0, image.height() as f32),
+            )
+        };
+        let visible = (
+            corner(margin.0, margin.1),
+            corner(f64::from(width) - margin.0, f64::from(height) - margin.1),
+        );
+        canvas.minimap(minimap, visible, text_scale);
+    }
+    if let Some(histogram) = scene.histogram {
+        canvas.histogram(histogram);
+    }
+    if let Some(cursor) = scene.magnifier {
+        let (x, y) = placement.image_point(cursor);
+        canvas.magnifier(image, x, y, scene.background.0);
+    }
+    if let Some(x) = divider {
+        let left = i64::from(x) - i64::from(DIVIDER_WIDTH / 2);
+        canvas.blend_rect(left, 0, DIVIDER_WIDTH, height, DIVIDER_COLOR, 0xff);
+    }
+    if let Some(help) = &scene.help {
+        canvas.help(help, text_scale);
+    }
+    if scene.drop_hint {
+        canvas.border(DROP_HINT_THICKNESS, DROP_HINT_COLOR);
+    }
+    pixels.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn opening_a_file_ends_the_comparison() {
+        let config = Config::parse_from(["img-vwr", "--compare", "a.png", "b.png"]);
+        let playlist = Playlist::new(vec![Source::File(PathBuf::from("a.png"))]);
+        let mut viewer = Viewer::new(config, Keymap::new(&BTreeMap::new()), playlist);
+        viewer.comparison = Some(Comparison::new(
+            Source::File(PathBuf::from("b.png")),
+            RgbaImage::new(2, 2),
+            viewer.initial_transform,
+            viewer.backdrop,
+        ));
+
+        // Its directory can't be listed, so it's the only image.
+        let dropped = env::temp_dir().join("img-vwr-missing").join("dropped.png");
+        assert!(matches!(viewer.open(dropped.clone()), Step::Stay));
+        assert!(viewer.comparison.is_none());
+        assert_eq!(viewer.playlist.len(), 1);
+        assert_eq!(
+            viewer.playlist.current().to_string(),
+            dropped.display().to_string()
+        );
+    }
 }
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use image::{imageops, Rgba, RgbaImage};
+
+use crate::picture::Frame;
+
+/// Width and height of the square every thumbnail is shrunk to fit in.
+pub const THUMBNAIL_SIZE: u32 = 160;
+/// Space around each thumbnail.
+const GAP: u32 = 8;
+const CELL_SIZE: u32 = THUMBNAIL_SIZE + 2 * GAP;
+
+/// A contact sheet: thumbnails of a list of images tiled into a grid, row by row.
+#[derive(Debug)]
+pub struct Montage {
+    image: RgbaImage,
+    columns: u32,
+    count: usize,
+}
+
+impl Montage {
+    /// Tiles `thumbnails`, none larger than `THUMBNAIL_SIZE` on either side, into `columns`
+    /// columns over `background`. Each one is centered in its cell.
+    pub fn new(thumbnails: &[RgbaImage], columns: u32, background: [u8; 4]) -> Self {
+        let count = thumbnails.len();
+        let columns = columns.clamp(1, (count as u32).max(1));
+        let rows = (count as u32).div_ceil(columns).max(1);
+        let mut image =
+            RgbaImage::from_pixel(columns * CELL_SIZE, rows * CELL_SIZE, Rgba(background));
+        for (i, thumbnail) in thumbnails.iter().enumerate() {
+            let (column, row) = (i as u32 % columns, i as u32 / columns);
+            let x = column * CELL_SIZE + (CELL_SIZE - thumbnail.width()) / 2;
+            let y = row * CELL_SIZE + (CELL_SIZE - thumbnail.height()) / 2;
+            imageops::overlay(&mut image, thumbnail, i64::from(x), i64::from(y));
+        }
+        Self {
+            image,
+            columns,
+            count,
+        }
+    }
+
+    /// The whole sheet as a still frame.
+    pub fn frame(&self) -> Frame {
+        Frame {
+            buffer: self.image.clone(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Which image the cell at (`x`, `y`), in pixels of the sheet, belongs to.
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<usize> {
+        if x < 0.0 || y < 0.0 || x >= self.image.width() as f32 {
+            return None;
+        }
+        let (column, row) = (x as u32 / CELL_SIZE, y as u32 / CELL_SIZE);
+        let index = (row * self.columns + column) as usize;
+        (index < self.count).then_some(index)
+    }
+}
+
+/// Shrinks `image` to fit in a `THUMBNAIL_SIZE` square, keeping its aspect ratio. Images that
+/// are already small enough are left as they are.
+pub fn thumbnail(image: &RgbaImage) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    if width <= THUMBNAIL_SIZE && height <= THUMBNAIL_SIZE {
+        return image.clone();
+    }
+    let scale = THUMBNAIL_SIZE as f32 / width.max(height) as f32;
+    let size = |side: u32| ((side as f32 * scale).round() as u32).clamp(1, THUMBNAIL_SIZE);
+    imageops::thumbnail(image, size(width), size(height))
+}
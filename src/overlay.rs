@@ -0,0 +1,392 @@
+use font8x8::{UnicodeFonts, BASIC_FONTS, LATIN_FONTS};
+use image::{imageops, RgbaImage};
+
+/// Width and height of a glyph of the bitmap font, before scaling.
+pub const GLYPH_SIZE: u32 = 8;
+/// Space between the edge of a bar and its text, before scaling.
+const BAR_PADDING: u32 = 3;
+const BAR_COLOR: [u8; 3] = [0, 0, 0];
+const BAR_ALPHA: u8 = 0xa0;
+const TEXT_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+/// How many image pixels across the magnifier shows. Odd, so that one is right in the middle.
+const MAGNIFIED_PIXELS: u32 = 15;
+/// How many canvas pixels wide each magnified image pixel is.
+const MAGNIFICATION: u32 = 10;
+/// Space between the magnifier and the corner of the canvas.
+const MAGNIFIER_MARGIN: u32 = 8;
+const MAGNIFIER_BORDER_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+/// How tall the histogram is, the tallest bin reaching the top. It's one pixel per bin wide.
+const HISTOGRAM_HEIGHT: u32 = 100;
+/// Space between the histogram and the corner of the canvas.
+const HISTOGRAM_MARGIN: u32 = 8;
+const HISTOGRAM_ALPHA: u8 = 0xc0;
+/// How much a curve adds to its channel.
+const HISTOGRAM_INTENSITY: u8 = 0xd0;
+
+/// Longest side of the minimap.
+const MINIMAP_SIZE: u32 = 160;
+/// Space between the minimap and the corner of the canvas, or the status bar.
+const MINIMAP_MARGIN: u32 = 8;
+const MINIMAP_BORDER_COLOR: [u8; 3] = [0x80, 0x80, 0x80];
+const MINIMAP_VIEWPORT_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+/// How much the image is darkened behind the help.
+const HELP_DIM_ALPHA: u8 = 0xc0;
+/// Space around the help and between its columns, before scaling.
+const HELP_MARGIN: u32 = 8;
+/// Space between lines of the help, before scaling.
+const HELP_LINE_GAP: u32 = 2;
+
+/// How many pixels of an image have each value, per color channel.
+#[derive(Debug)]
+pub struct Histogram {
+    /// Red, green and blue counts, indexed by channel value. Every pixel counts by its alpha.
+    counts: [[u64; 256]; 3],
+}
+
+impl Histogram {
+    /// Counts the colors of `image`, before it's put over a backdrop: fully transparent pixels
+    /// don't count, translucent ones only as much as they're opaque.
+    pub fn new(image: &RgbaImage) -> Self {
+        let mut counts = [[0; 256]; 3];
+        for pixel in image.pixels() {
+            let [r, g, b, alpha] = pixel.0;
+            for (channel, value) in counts.iter_mut().zip([r, g, b]) {
+                channel[usize::from(value)] += u64::from(alpha);
+            }
+        }
+        Self { counts }
+    }
+}
+
+/// The whole image shrunk into a corner, to show which part of it is in the window.
+#[derive(Debug)]
+pub struct Minimap {
+    thumbnail: RgbaImage,
+    /// Minimap pixels per image pixel.
+    scale: f32,
+}
+
+impl Minimap {
+    pub fn new(image: &RgbaImage) -> Self {
+        let scale = MINIMAP_SIZE as f32 / image.width().max(image.height()) as f32;
+        let side = |side: u32| ((side as f32 * scale).round() as u32).max(1);
+        Self {
+            thumbnail: imageops::thumbnail(image, side(image.width()), side(image.height())),
+            scale,
+        }
+    }
+
+    /// The top left corner of the minimap on a `width` x `height` canvas: the bottom right
+    /// corner, clear of a status bar with text at `text_scale`.
+    fn corner(&self, width: u32, height: u32, text_scale: u32) -> (i64, i64) {
+        (
+            i64::from(width) - i64::from(self.thumbnail.width() + MINIMAP_MARGIN),
+            i64::from(height)
+                - i64::from(self.thumbnail.height() + MINIMAP_MARGIN + bar_height(text_scale)),
+        )
+    }
+
+    /// The image pixel under (`x`, `y`) on a `width` x `height` canvas, if that's on the
+    /// minimap.
+    pub fn image_point(
+        &self,
+        width: u32,
+        height: u32,
+        text_scale: u32,
+        x: f64,
+        y: f64,
+    ) -> Option<(f32, f32)> {
+        let (left, top) = self.corner(width, height, text_scale);
+        let (x, y) = ((x - left as f64) as f32, (y - top as f64) as f32);
+        let inside = (0.0..self.thumbnail.width() as f32).contains(&x)
+            && (0.0..self.thumbnail.height() as f32).contains(&y);
+        inside.then(|| (x / self.scale, y / self.scale))
+    }
+}
+
+/// An RGBA frame buffer to draw overlays on top of.
+pub struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: u32,
+    height: u32,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(frame: &'a mut [u8], width: u32, height: u32) -> Self {
+        debug_assert_eq!(frame.len(), width as usize * height as usize * 4);
+        Self {
+            frame,
+            width,
+            height,
+        }
+    }
+
+    /// Blends `color` with opacity `alpha` over the rectangle at (`x`, `y`), clipped to the
+    /// canvas.
+    pub fn blend_rect(
+        &mut self,
+        x: i64,
+        y: i64,
+        width: u32,
+        height: u32,
+        color: [u8; 3],
+        alpha: u8,
+    ) {
+        let x_range = x.max(0)..(x + i64::from(width)).min(i64::from(self.width));
+        let y_range = y.max(0)..(y + i64::from(height)).min(i64::from(self.height));
+        for y in y_range {
+            for x in x_range.clone() {
+                self.blend_pixel(x as u32, y as u32, color, alpha);
+            }
+        }
+    }
+
+    /// Draws `text` with its top left corner at (`x`, `y`), each font pixel blown up to a
+    /// `scale` x `scale` square. Characters the font lacks are drawn as `?`.
+    pub fn draw_text(&mut self, x: i64, y: i64, text: &str, color: [u8; 3], scale: u32) {
+        let advance = i64::from(GLYPH_SIZE * scale);
+        for (i, c) in text.chars().enumerate() {
+            let glyph = BASIC_FONTS
+                .get(c)
+                .or_else(|| LATIN_FONTS.get(c))
+                .or_else(|| BASIC_FONTS.get('?'))
+                .unwrap_or_default();
+            let glyph_x = x + i as i64 * advance;
+            if glyph_x >= i64::from(self.width) {
+                break;
+            }
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..GLYPH_SIZE {
+                    if bits & (1 << column) != 0 {
+                        self.blend_rect(
+                            glyph_x + i64::from(column * scale),
+                            y + row as i64 * i64::from(scale),
+                            scale,
+                            scale,
+                            color,
+                            0xff,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws a semi-transparent bar across the bottom of the canvas holding `text`.
+    pub fn status_bar(&mut self, text: &str, scale: u32) {
+        let height = bar_height(scale);
+        let top = i64::from(self.height) - i64::from(height);
+        self.blend_rect(0, top, self.width, height, BAR_COLOR, BAR_ALPHA);
+        let padding = i64::from(BAR_PADDING * scale);
+        self.draw_text(padding, top + padding, text, TEXT_COLOR, scale);
+    }
+
+    /// Dims the whole canvas and lists `lines` over it, in as many columns as it takes to fit
+    /// them. The text gets smaller rather than running off the canvas, as far as it can.
+    pub fn help(&mut self, lines: &[String], scale: u32) {
+        self.blend_rect(0, 0, self.width, self.height, BAR_COLOR, HELP_DIM_ALPHA);
+        let longest = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0) as u32;
+        let layout = |scale: u32| {
+            let line_height = (GLYPH_SIZE + HELP_LINE_GAP) * scale;
+            let rows = (self.height.saturating_sub(2 * HELP_MARGIN * scale) / line_height).max(1);
+            let columns = (lines.len() as u32).div_ceil(rows);
+            let column_width = (longest * GLYPH_SIZE + HELP_MARGIN) * scale;
+            (
+                line_height,
+                rows,
+                column_width,
+                columns * column_width <= self.width,
+            )
+        };
+        let scale = (1..=scale)
+            .rev()
+            .find(|&scale| layout(scale).3)
+            .unwrap_or(1);
+        let (line_height, rows, column_width, _) = layout(scale);
+        let margin = i64::from(HELP_MARGIN * scale);
+        for (i, line) in lines.iter().enumerate() {
+            let (column, row) = (i as u32 / rows, i as u32 % rows);
+            let x = margin + i64::from(column * column_width);
+            let y = margin + i64::from(row * line_height);
+            self.draw_text(x, y, line, TEXT_COLOR, scale);
+        }
+    }
+
+    /// Draws `minimap` in the bottom right corner, framing the part of the image between the
+    /// corners `visible`, in image pixels.
+    pub fn minimap(
+        &mut self,
+        minimap: &Minimap,
+        visible: ((f32, f32), (f32, f32)),
+        text_scale: u32,
+    ) {
+        let (left, top) = minimap.corner(self.width, self.height, text_scale);
+        for (x, y, pixel) in minimap.thumbnail.enumerate_pixels() {
+            let [r, g, b, _] = pixel.0;
+            self.blend_rect(
+                left + i64::from(x),
+                top + i64::from(y),
+                1,
+                1,
+                [r, g, b],
+                0xff,
+            );
+        }
+        let (width, height) = minimap.thumbnail.dimensions();
+        self.outline(
+            left - 1,
+            top - 1,
+            width + 2,
+            height + 2,
+            1,
+            MINIMAP_BORDER_COLOR,
+        );
+        let scaled = |value: f32| (value * minimap.scale).round() as i64;
+        let ((x0, y0), (x1, y1)) = visible;
+        self.outline(
+            left + scaled(x0),
+            top + scaled(y0),
+            (scaled(x1) - scaled(x0)).max(1) as u32,
+            (scaled(y1) - scaled(y0)).max(1) as u32,
+            1,
+            MINIMAP_VIEWPORT_COLOR,
+        );
+    }
+
+    /// Outlines the canvas with a `thickness` pixels wide `color` frame.
+    pub fn border(&mut self, thickness: u32, color: [u8; 3]) {
+        self.outline(0, 0, self.width, self.height, thickness, color);
+    }
+
+    /// Draws a `thickness` pixels wide `color` frame just inside the rectangle at (`x`, `y`).
+    pub fn outline(
+        &mut self,
+        x: i64,
+        y: i64,
+        width: u32,
+        height: u32,
+        thickness: u32,
+        color: [u8; 3],
+    ) {
+        let bottom = y + i64::from(height) - i64::from(thickness);
+        let right = x + i64::from(width) - i64::from(thickness);
+        self.blend_rect(x, y, width, thickness, color, 0xff);
+        self.blend_rect(x, bottom, width, thickness, color, 0xff);
+        self.blend_rect(x, y, thickness, height, color, 0xff);
+        self.blend_rect(right, y, thickness, height, color, 0xff);
+    }
+
+    /// Shows the pixels of `image` around (`x`, `y`), in image pixels, blown up in the top right
+    /// corner. Whatever is beyond the edges of the image is shown as `background`.
+    pub fn magnifier(&mut self, image: &RgbaImage, x: f32, y: f32, background: [u8; 3]) {
+        let size = MAGNIFIED_PIXELS * MAGNIFICATION;
+        let left = i64::from(self.width) - i64::from(size + MAGNIFIER_MARGIN);
+        let top = i64::from(MAGNIFIER_MARGIN);
+        let radius = i64::from(MAGNIFIED_PIXELS / 2);
+        let (center_x, center_y) = (x.floor() as i64, y.floor() as i64);
+        for row in 0..MAGNIFIED_PIXELS {
+            for column in 0..MAGNIFIED_PIXELS {
+                let src_x = center_x - radius + i64::from(column);
+                let src_y = center_y - radius + i64::from(row);
+                let inside = (0..i64::from(image.width())).contains(&src_x)
+                    && (0..i64::from(image.height())).contains(&src_y);
+                let color = if inside {
+                    let [r, g, b, _] = image.get_pixel(src_x as u32, src_y as u32).0;
+                    [r, g, b]
+                } else {
+                    background
+                };
+                self.blend_rect(
+                    left + i64::from(column * MAGNIFICATION),
+                    top + i64::from(row * MAGNIFICATION),
+                    MAGNIFICATION,
+                    MAGNIFICATION,
+                    color,
+                    0xff,
+                );
+            }
+        }
+        // Frame the pixel under the cursor, and the whole magnifier.
+        let middle = i64::from(MAGNIFIED_PIXELS / 2 * MAGNIFICATION);
+        self.outline(
+            left + middle,
+            top + middle,
+            MAGNIFICATION,
+            MAGNIFICATION,
+            1,
+            MAGNIFIER_BORDER_COLOR,
+        );
+        self.outline(
+            left - 1,
+            top - 1,
+            size + 2,
+            size + 2,
+            1,
+            MAGNIFIER_BORDER_COLOR,
+        );
+    }
+
+    /// Draws the curves of the three channels of `histogram` in the top left corner, adding up
+    /// where they overlap so that all three together show as white.
+    pub fn histogram(&mut self, histogram: &Histogram) {
+        let left = i64::from(HISTOGRAM_MARGIN);
+        let top = i64::from(HISTOGRAM_MARGIN);
+        self.blend_rect(left, top, 256, HISTOGRAM_HEIGHT, BAR_COLOR, HISTOGRAM_ALPHA);
+        let tallest = histogram
+            .counts
+            .iter()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let bottom = top + i64::from(HISTOGRAM_HEIGHT);
+        for (channel, counts) in histogram.counts.iter().enumerate() {
+            for (value, &count) in counts.iter().enumerate() {
+                let height = (count * u64::from(HISTOGRAM_HEIGHT)).div_ceil(tallest);
+                let x = left + value as i64;
+                for y in bottom - height as i64..bottom {
+                    self.add_to_channel(x, y, channel);
+                }
+            }
+        }
+    }
+
+    /// Brightens `channel` of the pixel at (`x`, `y`) by a curve of the histogram, if it's on
+    /// the canvas.
+    fn add_to_channel(&mut self, x: i64, y: i64, channel: usize) {
+        if !(0..i64::from(self.width)).contains(&x) || !(0..i64::from(self.height)).contains(&y) {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        self.frame[i + channel] = self.frame[i + channel].saturating_add(HISTOGRAM_INTENSITY);
+    }
+
+    fn blend_pixel(&mut self, x: u32, y: u32, color: [u8; 3], alpha: u8) {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let alpha = u32::from(alpha);
+        for (channel, &value) in self.frame[i..i + 3].iter_mut().zip(&color) {
+            *channel =
+                ((u32::from(*channel) * (255 - alpha) + u32::from(value) * alpha) / 255) as u8;
+        }
+    }
+}
+
+/// How tall the status bar is with text at `scale`.
+pub fn bar_height(scale: u32) -> u32 {
+    (GLYPH_SIZE + 2 * BAR_PADDING) * scale
+}
+
+/// The font scale that keeps overlay text readable without crowding a window `width` pixels
+/// wide.
+pub fn text_scale(width: u32) -> u32 {
+    if width >= 800 {
+        2
+    } else {
+        1
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use image::{
+    codecs::{
+        bmp::BmpDecoder, gif::GifDecoder, jpeg::JpegDecoder, png::PngDecoder, tiff::TiffDecoder,
+        webp::WebPDecoder,
+    },
+    imageops,
+    io::Reader,
+    AnimationDecoder, ColorType, DynamicImage, Frames, ImageBuffer, ImageDecoder, ImageFormat,
+    Rgba, RgbaImage,
+};
+use tiff::decoder::{Decoder as TiffPages, DecodingResult};
+
+use crate::{archive, icc, svg, video, Result, RvuError};
+
+/// How long fetching an image from a URL may take before giving up.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Browsers stretch GIF frames with (almost) no delay to this, and plenty of GIFs rely on it.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+/// Size of the placeholder, and of the window for an image whose size can't be read from its
+/// header.
+pub const PLACEHOLDER_SIZE: (u32, u32) = (640, 480);
+const PLACEHOLDER_COLOR: Rgba<u8> = Rgba([0x80, 0x10, 0x10, 0xff]);
+const LOADING_COLOR: Rgba<u8> = Rgba([0x20, 0x20, 0x20, 0xff]);
+
+/// Where an image comes from.
+#[derive(Debug, Clone)]
+pub enum Source {
+    File(PathBuf),
+    /// An image that has already been read into memory, such as one piped in on stdin or
+    /// downloaded.
+    Memory {
+        name: String,
+        bytes: Arc<[u8]>,
+    },
+    /// An image in a ZIP archive, read out of it whenever it's loaded.
+    Archived {
+        archive: PathBuf,
+        entry: String,
+    },
+}
+
+impl Source {
+    /// Reads all of stdin as a single image.
+    pub fn stdin() -> Result<Self> {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        if bytes.is_empty() {
+            return Err(RvuError::EmptyStdin);
+        }
+        Ok(Self::Memory {
+            name: "(stdin)".to_string(),
+            bytes: bytes.into(),
+        })
+    }
+
+    /// Downloads the image at `url`.
+    pub fn url(url: &str) -> Result<Self> {
+        let agent = ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).build();
+        let mut bytes = Vec::new();
+        agent
+            .get(url)
+            .call()?
+            .into_reader()
+            .read_to_end(&mut bytes)?;
+        Ok(Self::Memory {
+            name: url.to_string(),
+            bytes: bytes.into(),
+        })
+    }
+
+    /// Where an edited copy of the image is saved in `format`: next to the original as
+    /// `original.edited.png`, or in the current directory for images that aren't files.
+    pub fn edited_path(&self, format: ImageFormat) -> PathBuf {
+        let name = |stem: &str| format!("{}.edited.{}", stem, format.extensions_str()[0]);
+        match self {
+            Self::File(path) => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                path.with_file_name(name(&stem))
+            }
+            // The last part of a URL or of the name in an archive is usually a file name, stdin
+            // has none.
+            Self::Memory { name: source, .. } | Self::Archived { entry: source, .. } => {
+                let stem = Path::new(source.rsplit('/').next().unwrap_or_default())
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .filter(|stem| !stem.is_empty() && !stem.starts_with('('))
+                    .unwrap_or("image");
+                PathBuf::from(name(stem))
+            }
+        }
+    }
+
+    /// The image in memory, read out of its archive if it's in one, so that everything read from
+    /// it after that doesn't unpack it all over again.
+    pub fn extract(&self) -> Result<Self> {
+        match self {
+            Self::Archived { archive, entry } => Ok(Self::Memory {
+                name: self.to_string(),
+                bytes: archive::read(archive, entry)?.into(),
+            }),
+            _ => Ok(self.clone()),
+        }
+    }
+}
+
+/// Whether `name` looks like a URL to download rather than a path.
+pub fn is_url(name: &str) -> bool {
+    name.starts_with("http://") || name.starts_with("https://")
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => path.display().fmt(f),
+            Self::Memory { name, .. } => name.fmt(f),
+            Self::Archived { archive, entry } => write!(f, "{}/{}", archive.display(), entry),
+        }
+    }
+}
+
+/// A single frame of a decoded image, in the 8-bit RGBA layout of the pixel buffer.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub buffer: RgbaImage,
+    /// How long the frame stays on screen before the next one of an animation is shown. Zero
+    /// for the pages of a document, which don't turn by themselves.
+    pub delay: Duration,
+}
+
+/// Whether `frames` play as an animation, rather than being a still image or pages.
+pub fn is_animation(frames: &[Frame]) -> bool {
+    frames.len() > 1 && frames[0].delay > Duration::ZERO
+}
+
+/// Decodes the image from `source`. Animated GIFs, PNGs and WebPs yield all of their frames, and
+/// TIFFs all of their pages, every other image a single frame, whatever the decoder produced
+/// (grayscale, RGB, 16-bit, ...). Deeper images are scaled down to 8 bits per channel. With
+/// `autorotate`, the frames are turned the way the EXIF orientation tag says the camera was
+/// held. SVGs are rasterized at the size they declare.
+pub fn load(source: &Source, autorotate: bool) -> Result<Vec<Frame>> {
+    if svg::is_svg(source) {
+        return svg::load(source, 1.0);
+    }
+    // Rather than whatever the image decoders make of it.
+    if matches!(source, Source::File(path) if video::is_video(path)) {
+        return Err(RvuError::VideoNotSupported);
+    }
+    let (mut frames, orientation) = match source {
+        Source::File(path) => {
+            let orientation = autorotate
+                .then(|| File::open(path).ok())
+                .flatten()
+                .and_then(|file| exif_orientation(&mut BufReader::new(file)));
+            (decode(Reader::open(path)?)?, orientation)
+        }
+        Source::Memory { bytes, .. } => {
+            let orientation = autorotate
+                .then(|| exif_orientation(&mut Cursor::new(&bytes[..])))
+                .flatten();
+            let reader = Reader::new(Cursor::new(&bytes[..])).with_guessed_format()?;
+            (decode(reader)?, orientation)
+        }
+        Source::Archived { .. } => return load(&source.extract()?, autorotate),
+    };
+
+    // Some decoders happily produce these, but there's nothing to show.
+    if frames
+        .iter()
+        .any(|frame| frame.buffer.width() == 0 || frame.buffer.height() == 0)
+    {
+        return Err(RvuError::EmptyImage);
+    }
+    if let Some(orientation) = orientation {
+        for frame in &mut frames {
+            frame.buffer = orient(&frame.buffer, orientation);
+        }
+    }
+    Ok(frames)
+}
+
+/// Reads the EXIF orientation tag, if the image has one.
+fn exif_orientation<R: BufRead + Seek>(reader: &mut R) -> Option<u32> {
+    let exif = exif::Reader::new().read_from_container(reader).ok()?;
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+        .value
+        .get_uint(0)
+}
+
+/// Applies an EXIF `orientation` (1 to 8) so that the image ends up upright.
+fn orient(image: &RgbaImage, orientation: u32) -> RgbaImage {
+    match orientation {
+        2 => imageops::flip_horizontal(image),
+        3 => imageops::rotate180(image),
+        4 => imageops::flip_vertical(image),
+        5 => imageops::flip_horizontal(&imageops::rotate90(image)),
+        6 => imageops::rotate90(image),
+        7 => imageops::flip_horizontal(&imageops::rotate270(image)),
+        8 => imageops::rotate270(image),
+        _ => image.clone(),
+    }
+}
+
+/// What `--info` reports about an image.
+#[derive(Debug)]
+pub struct Info {
+    /// The name of the format, if it could be recognized.
+    pub format: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub color_type: ColorType,
+    /// The name of the embedded ICC profile, if there is one.
+    pub color_profile: Option<String>,
+    /// Size of the encoded image in bytes.
+    pub size: u64,
+}
+
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.format {
+            Some(format) => writeln!(f, "format: {}", format)?,
+            None => writeln!(f, "format: unknown")?,
+        }
+        writeln!(f, "width: {}", self.width)?;
+        writeln!(f, "height: {}", self.height)?;
+        writeln!(f, "color type: {:?}", self.color_type)?;
+        writeln!(
+            f,
+            "bit depth: {} bits per channel",
+            self.color_type.bits_per_pixel() / u16::from(self.color_type.channel_count())
+        )?;
+        match &self.color_profile {
+            Some(name) if icc::is_srgb(name) => writeln!(f, "color profile: {}", name)?,
+            Some(name) => writeln!(f, "color profile: {} (not sRGB)", name)?,
+            None => writeln!(f, "color profile: none")?,
+        }
+        write!(f, "file size: {} bytes", self.size)
+    }
+}
+
+/// Reads the format, dimensions and color type of the image from `source`. Common formats only
+/// have their header read, the others are decoded to find out.
+pub fn info(source: &Source) -> Result<Info> {
+    if svg::is_svg(source) {
+        let (width, height) = svg::size(source)?;
+        let size = match source {
+            Source::File(path) => path.metadata()?.len(),
+            Source::Memory { bytes, .. } => bytes.len() as u64,
+            Source::Archived { .. } => return info(&source.extract()?),
+        };
+        return Ok(Info {
+            format: Some("Svg".to_string()),
+            width,
+            height,
+            color_type: ColorType::Rgba8,
+            color_profile: None,
+            size,
+        });
+    }
+    let info = match source {
+        Source::File(path) => {
+            let size = path.metadata()?.len();
+            read_info(Reader::open(path)?.with_guessed_format()?, size)?
+        }
+        Source::Memory { bytes, .. } => {
+            let reader = Reader::new(Cursor::new(&bytes[..])).with_guessed_format()?;
+            read_info(reader, bytes.len() as u64)?
+        }
+        Source::Archived { .. } => return info(&source.extract()?),
+    };
+    Ok(Info {
+        color_profile: color_profile(source),
+        ..info
+    })
+}
+
+/// The name of the ICC profile embedded in the image from `source`, or `unnamed` if it has one
+/// that doesn't say. Only PNGs, JPEGs, TIFFs and WebPs are looked into.
+pub fn color_profile(source: &Source) -> Option<String> {
+    if svg::is_svg(source) {
+        return None;
+    }
+    let profile = match source {
+        Source::File(path) => {
+            embedded_profile(Reader::open(path).ok()?.with_guessed_format().ok()?)
+        }
+        Source::Memory { bytes, .. } => embedded_profile(
+            Reader::new(Cursor::new(&bytes[..]))
+                .with_guessed_format()
+                .ok()?,
+        ),
+        Source::Archived { .. } => return color_profile(&source.extract().ok()?),
+    }
+    .filter(|profile| !profile.is_empty())?;
+    Some(icc::profile_name(&profile).unwrap_or_else(|| "unnamed".to_string()))
+}
+
+fn embedded_profile<R: BufRead + Seek>(reader: Reader<R>) -> Option<Vec<u8>> {
+    match reader.format()? {
+        ImageFormat::Png => PngDecoder::new(reader.into_inner()).ok()?.icc_profile(),
+        ImageFormat::Jpeg => JpegDecoder::new(reader.into_inner()).ok()?.icc_profile(),
+        ImageFormat::Tiff => TiffDecoder::new(reader.into_inner()).ok()?.icc_profile(),
+        ImageFormat::WebP => WebPDecoder::new(reader.into_inner()).ok()?.icc_profile(),
+        _ => None,
+    }
+}
+
+fn read_info<R: BufRead + Seek>(reader: Reader<R>, size: u64) -> Result<Info> {
+    let format = reader.format();
+    let (width, height, color_type) = match format {
+        Some(ImageFormat::Png) => header(PngDecoder::new(reader.into_inner())?),
+        Some(ImageFormat::Jpeg) => header(JpegDecoder::new(reader.into_inner())?),
+        Some(ImageFormat::Gif) => header(GifDecoder::new(reader.into_inner())?),
+        Some(ImageFormat::Bmp) => header(BmpDecoder::new(reader.into_inner())?),
+        Some(ImageFormat::Tiff) => header(TiffDecoder::new(reader.into_inner())?),
+        Some(ImageFormat::WebP) => header(WebPDecoder::new(reader.into_inner())?),
+        _ => {
+            let image = reader.decode()?;
+            (image.width(), image.height(), image.color())
+        }
+    };
+    Ok(Info {
+        format: format.map(|format| format!("{:?}", format)),
+        width,
+        height,
+        color_type,
+        color_profile: None,
+        size,
+    })
+}
+
+fn header<'a>(decoder: impl ImageDecoder<'a>) -> (u32, u32, ColorType) {
+    let (width, height) = decoder.dimensions();
+    (width, height, decoder.color_type())
+}
+
+/// Stands in for an image that failed to load: a single plain red frame.
+pub fn placeholder() -> Vec<Frame> {
+    vec![Frame {
+        buffer: RgbaImage::from_pixel(PLACEHOLDER_SIZE.0, PLACEHOLDER_SIZE.1, PLACEHOLDER_COLOR),
+        delay: Duration::ZERO,
+    }]
+}
+
+/// Stands in for an image that's still being decoded: a plain dark `width` x `height` frame.
+pub fn loading(width: u32, height: u32) -> Vec<Frame> {
+    vec![Frame {
+        buffer: RgbaImage::from_pixel(width, height, LOADING_COLOR),
+        delay: Duration::ZERO,
+    }]
+}
+
+fn decode<R: BufRead + Seek>(reader: Reader<R>) -> Result<Vec<Frame>> {
+    match reader.format() {
+        Some(ImageFormat::Gif) => animation(GifDecoder::new(reader.into_inner())?.into_frames()),
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(reader.into_inner())?;
+            if decoder.is_apng() {
+                animation(decoder.apng().into_frames())
+            } else {
+                still(DynamicImage::from_decoder(decoder)?)
+            }
+        }
+        Some(ImageFormat::Tiff) => {
+            let mut reader = reader.into_inner();
+            let start = reader.stream_position()?;
+            match tiff_pages(&mut reader) {
+                Some(pages) if pages.len() > 1 => Ok(pages),
+                // A single page is better left to `image`, which knows more color types.
+                _ => {
+                    reader.seek(SeekFrom::Start(start))?;
+                    still(DynamicImage::from_decoder(TiffDecoder::new(reader)?)?)
+                }
+            }
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(reader.into_inner())?;
+            if decoder.has_animation() {
+                animation(decoder.into_frames())
+            } else {
+                still(DynamicImage::from_decoder(decoder)?)
+            }
+        }
+        _ => still(reader.decode()?),
+    }
+}
+
+/// Decodes every page of a TIFF, up to the first one that can't be.
+fn tiff_pages<R: Read + Seek>(reader: R) -> Option<Vec<Frame>> {
+    let mut decoder = TiffPages::new(reader).ok()?;
+    let mut pages = Vec::new();
+    while let Some(buffer) = tiff_page(&mut decoder) {
+        pages.push(Frame {
+            buffer,
+            delay: Duration::ZERO,
+        });
+        if !decoder.more_images() || decoder.next_image().is_err() {
+            break;
+        }
+    }
+    Some(pages)
+}
+
+/// Decodes the page the TIFF `decoder` is on, if it's in one of the common color types.
+fn tiff_page<R: Read + Seek>(decoder: &mut TiffPages<R>) -> Option<RgbaImage> {
+    use tiff::ColorType::{Gray, GrayA, RGB, RGBA};
+    let (width, height) = decoder.dimensions().ok()?;
+    let image = match (decoder.colortype().ok()?, decoder.read_image().ok()?) {
+        (Gray(8), DecodingResult::U8(data)) => {
+            DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, data)?)
+        }
+        (Gray(16), DecodingResult::U16(data)) => {
+            DynamicImage::ImageLuma16(ImageBuffer::from_raw(width, height, data)?)
+        }
+        (GrayA(8), DecodingResult::U8(data)) => {
+            DynamicImage::ImageLumaA8(ImageBuffer::from_raw(width, height, data)?)
+        }
+        (GrayA(16), DecodingResult::U16(data)) => {
+            DynamicImage::ImageLumaA16(ImageBuffer::from_raw(width, height, data)?)
+        }
+        (RGB(8), DecodingResult::U8(data)) => {
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, data)?)
+        }
+        (RGB(16), DecodingResult::U16(data)) => {
+            DynamicImage::ImageRgb16(ImageBuffer::from_raw(width, height, data)?)
+        }
+        (RGBA(8), DecodingResult::U8(data)) => {
+            DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, data)?)
+        }
+        (RGBA(16), DecodingResult::U16(data)) => {
+            DynamicImage::ImageRgba16(ImageBuffer::from_raw(width, height, data)?)
+        }
+        _ => return None,
+    };
+    Some(image.to_rgba8())
+}
+
+fn still(image: DynamicImage) -> Result<Vec<Frame>> {
+    Ok(vec![Frame {
+        buffer: image.to_rgba8(),
+        delay: Duration::ZERO,
+    }])
+}
+
+/// Collects all frames of an animated GIF, PNG or WebP.
+fn animation(frames: Frames) -> Result<Vec<Frame>> {
+    let frames = frames.collect_frames()?;
+    if frames.is_empty() {
+        return Err(RvuError::EmptyImage);
+    }
+    Ok(frames
+        .into_iter()
+        .map(|frame| {
+            let delay = Duration::from(frame.delay());
+            Frame {
+                buffer: frame.into_buffer(),
+                delay: if delay < MIN_FRAME_DELAY {
+                    DEFAULT_FRAME_DELAY
+                } else {
+                    delay
+                },
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,332 @@
+use std::{
+    cmp::Ordering,
+    fs, io,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+    time::SystemTime,
+};
+
+use clap::ArgEnum;
+use image::ImageFormat;
+
+use crate::{picture::Source, svg};
+
+/// The order the images of a directory are viewed in.
+#[derive(ArgEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// By path, character by character.
+    Name,
+    /// By path, ignoring case, with runs of digits compared as numbers so that `img2` comes before
+    /// `img10`.
+    #[default]
+    Natural,
+    /// Oldest modification first.
+    Mtime,
+    /// Smallest file first.
+    Size,
+}
+
+impl SortOrder {
+    pub fn sort(self, paths: &mut [PathBuf]) {
+        match self {
+            SortOrder::Name => paths.sort(),
+            SortOrder::Natural => paths.sort_by(|a, b| {
+                natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()).then_with(|| a.cmp(b))
+            }),
+            // Files that can't be looked at anymore go first, they'll fail to load anyway.
+            SortOrder::Mtime => paths.sort_by_cached_key(|path| {
+                let modified = path.metadata().and_then(|metadata| metadata.modified());
+                (modified.unwrap_or(SystemTime::UNIX_EPOCH), path.clone())
+            }),
+            SortOrder::Size => paths.sort_by_cached_key(|path| {
+                let size = path.metadata().map(|metadata| metadata.len());
+                (size.unwrap_or(0), path.clone())
+            }),
+        }
+    }
+}
+
+/// An ordered list of images to step through, with a cursor on the one being displayed.
+#[derive(Debug)]
+pub struct Playlist {
+    sources: Vec<Source>,
+    index: usize,
+}
+
+impl Playlist {
+    /// Builds a playlist of exactly `sources`, in the given order. `sources` must not be empty.
+    pub fn new(sources: Vec<Source>) -> Self {
+        assert!(!sources.is_empty(), "a playlist needs at least one image");
+        Self { sources, index: 0 }
+    }
+
+    /// Builds a playlist of every supported image in the directory containing `path`, in `order`
+    /// and positioned on `path` itself.
+    pub fn from_siblings(path: &Path, order: SortOrder) -> io::Result<Self> {
+        let parent = path.parent().unwrap_or_else(|| Path::new(""));
+        // `read_dir` doesn't accept the empty parent of a bare file name.
+        let dir = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+
+        let mut paths = Vec::new();
+        list_images(dir, false, &mut paths)?;
+        // Keep the paths relative to wherever `path` was, not to `.`.
+        if parent.as_os_str().is_empty() {
+            for path in &mut paths {
+                *path = path.strip_prefix(dir).unwrap_or(path).to_path_buf();
+            }
+        }
+        // The requested file may not have a recognised extension, but it was asked for explicitly
+        // so it always gets a place in the list.
+        if !paths.iter().any(|p| p.file_name() == path.file_name()) {
+            paths.push(path.to_path_buf());
+        }
+        order.sort(&mut paths);
+        let index = paths
+            .iter()
+            .position(|p| p.file_name() == path.file_name())
+            .expect("the requested file was added");
+
+        let sources = paths.into_iter().map(Source::File).collect();
+        Ok(Self { sources, index })
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Where the current image is in the list, counting from 0.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    pub fn current(&self) -> &Source {
+        &self.sources[self.index]
+    }
+
+    /// The images `next` and `prev` would move to, in that order, other than the current one.
+    pub fn neighbors(&self) -> Vec<&Source> {
+        let len = self.sources.len();
+        let mut indices = vec![(self.index + 1) % len, (self.index + len - 1) % len];
+        indices.dedup();
+        indices.retain(|&index| index != self.index);
+        indices
+            .into_iter()
+            .map(|index| &self.sources[index])
+            .collect()
+    }
+
+    pub fn is_first(&self) -> bool {
+        self.index == 0
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.index + 1 == self.sources.len()
+    }
+
+    /// Moves to the next image, wrapping around to the first one after the last.
+    pub fn next(&mut self) -> &Source {
+        self.index = (self.index + 1) % self.sources.len();
+        self.current()
+    }
+
+    /// Moves to the image at `index`, which must be in the list.
+    pub fn go_to(&mut self, index: usize) -> &Source {
+        assert!(
+            index < self.sources.len(),
+            "no image {} in the playlist",
+            index
+        );
+        self.index = index;
+        self.current()
+    }
+
+    /// Drops the current image from the list and moves to the one after it, or returns `None`
+    /// if it was the last one left.
+    pub fn remove_current(&mut self) -> Option<&Source> {
+        if self.sources.len() == 1 {
+            return None;
+        }
+        self.sources.remove(self.index);
+        self.index %= self.sources.len();
+        Some(self.current())
+    }
+
+    /// Moves to the previous image, wrapping around to the last one before the first.
+    pub fn prev(&mut self) -> &Source {
+        self.index = (self.index + self.sources.len() - 1) % self.sources.len();
+        self.current()
+    }
+}
+
+/// Reads the newline separated paths listed in the file at `path`, or on stdin for `-`. Lines are
+/// trimmed and blank ones skipped.
+pub fn read_list(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let text = if path == Path::new("-") {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(path)?
+    };
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Lists every supported image in `dir` in `order`, and with `recursive` in all of its
+/// subdirectories too.
+pub fn scan_dir(dir: &Path, recursive: bool, order: SortOrder) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    list_images(dir, recursive, &mut paths)?;
+    order.sort(&mut paths);
+    Ok(paths)
+}
+
+/// Adds the supported images in `dir` to `paths`, in no particular order. Symlinked directories
+/// aren't followed, so that a link back up the tree can't recurse forever.
+fn list_images(dir: &Path, recursive: bool, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if recursive && entry.file_type()?.is_dir() {
+            list_images(&path, recursive, paths)?;
+        } else if path.is_file() && is_supported(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` has the extension of a format we are able to decode.
+pub fn is_supported(path: &Path) -> bool {
+    svg::has_svg_extension(path)
+        || ImageFormat::from_path(path)
+            .map(|format| format.can_read())
+            .unwrap_or(false)
+}
+
+/// Compares `a` and `b` character by character, ignoring case, except that runs of ASCII digits
+/// are compared by their numeric value. Names that only differ in case or leading zeros are equal.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek(), b.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let ordering = cmp_number(&take_digits(&mut a), &take_digits(&mut b));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
+
+/// Compares two runs of digits by value, however long they are.
+fn cmp_number(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut paths: Vec<PathBuf> = names.iter().map(PathBuf::from).collect();
+        SortOrder::Natural.sort(&mut paths);
+        paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn numbers_are_compared_by_value() {
+        assert_eq!(natural_cmp("img2.png", "img10.png"), Ordering::Less);
+        assert_eq!(natural_cmp("img10.png", "img2.png"), Ordering::Greater);
+        assert_eq!(
+            sorted(&["img10.png", "img2.png", "img1.png"]),
+            ["img1.png", "img2.png", "img10.png"]
+        );
+        assert_eq!(natural_cmp("a2b10", "a2b9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn leading_zeros_dont_count_but_still_sort_the_same_every_time() {
+        assert_eq!(natural_cmp("01", "1"), Ordering::Equal);
+        assert_eq!(natural_cmp("page007", "page7"), Ordering::Equal);
+        assert_eq!(natural_cmp("page007", "page8"), Ordering::Less);
+        let expected = ["01.png", "1.png", "2.png"];
+        assert_eq!(sorted(&["1.png", "01.png", "2.png"]), expected);
+        assert_eq!(sorted(&["2.png", "01.png", "1.png"]), expected);
+    }
+
+    #[test]
+    fn numbers_may_be_longer_than_fit_in_an_integer() {
+        let huge = "frame123456789012345678901234567890";
+        assert_eq!(
+            natural_cmp(huge, "frame18446744073709551615"),
+            Ordering::Greater
+        );
+        assert_eq!(natural_cmp(huge, huge), Ordering::Equal);
+        assert_eq!(
+            natural_cmp("frame123456789012345678901234567891", huge),
+            Ordering::Greater
+        );
+        assert_eq!(
+            natural_cmp(&format!("frame000{}", &huge[5..]), huge),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn case_is_ignored() {
+        assert_eq!(natural_cmp("b.png", "A.png"), Ordering::Greater);
+        assert_eq!(natural_cmp("Img2", "img10"), Ordering::Less);
+        assert_eq!(natural_cmp("ÉTÉ", "été"), Ordering::Equal);
+        assert_eq!(
+            sorted(&["c.png", "B.png", "a.png", "b.png"]),
+            ["a.png", "B.png", "b.png", "c.png"]
+        );
+    }
+
+    #[test]
+    fn names_without_digits_go_character_by_character() {
+        assert_eq!(natural_cmp("apple", "banana"), Ordering::Less);
+        assert_eq!(natural_cmp("apple", "apple"), Ordering::Equal);
+        // A prefix comes first.
+        assert_eq!(natural_cmp("app", "apple"), Ordering::Less);
+        assert_eq!(natural_cmp("", "a"), Ordering::Less);
+        // Letters come after digits, as they do in ASCII.
+        assert_eq!(natural_cmp("a1", "ab"), Ordering::Less);
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Instant,
+};
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::picture::{self, Frame, Source};
+
+/// An image decoded in the background, sent to the event loop as a user event.
+#[derive(Debug)]
+pub struct Decoded {
+    key: String,
+    result: Result<Loaded, String>,
+}
+
+/// The frames of a decoded image, and what else was read from it along the way.
+#[derive(Debug)]
+pub struct Loaded {
+    pub frames: Vec<Frame>,
+    /// The name of its ICC profile, if it has one.
+    pub color_profile: Option<String>,
+}
+
+/// Decodes the images around the current one on a background thread, so that stepping to them
+/// doesn't have to wait for the decoder. Images are told apart by their name.
+#[derive(Debug)]
+pub struct Preloader {
+    requests: Sender<Source>,
+    /// Decoded neighbors, or why they couldn't be decoded.
+    cache: HashMap<String, Result<Loaded, String>>,
+    /// Images sent off for decoding that haven't come back yet.
+    pending: HashSet<String>,
+    /// The images worth keeping decoded.
+    wanted: HashSet<String>,
+}
+
+impl Preloader {
+    /// Starts the decoding thread, which hands back what it decoded through `proxy`.
+    pub fn new<T>(proxy: EventLoopProxy<T>, autorotate: bool) -> Self
+    where
+        T: From<Decoded> + Send + 'static,
+    {
+        let (requests, received) = mpsc::channel::<Source>();
+        thread::spawn(move || {
+            for source in received {
+                let started = Instant::now();
+                // An image in an archive is unpacked once, for both the frames and the profile.
+                let result = source
+                    .extract()
+                    .and_then(|extracted| {
+                        Ok(Loaded {
+                            frames: picture::load(&extracted, autorotate)?,
+                            color_profile: picture::color_profile(&extracted),
+                        })
+                    })
+                    .map_err(|err| err.to_string());
+                match &result {
+                    Ok(Loaded { frames, .. }) => log::info!(
+                        "Decoded {} ({}x{}, {} frames) in {:?}",
+                        source,
+                        frames[0].buffer.width(),
+                        frames[0].buffer.height(),
+                        frames.len(),
+                        started.elapsed()
+                    ),
+                    Err(err) => log::info!("Failed to decode {}: {}", source, err),
+                }
+                let decoded = Decoded {
+                    key: source.to_string(),
+                    result,
+                };
+                // The event loop is gone, so nobody cares anymore.
+                if proxy.send_event(decoded.into()).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            requests,
+            cache: HashMap::new(),
+            pending: HashSet::new(),
+            wanted: HashSet::new(),
+        }
+    }
+
+    /// Whether `source` is decoded, or failed to.
+    pub fn is_ready(&self, source: &Source) -> bool {
+        self.cache.contains_key(&source.to_string())
+    }
+
+    /// Takes the already decoded image from `source` out of the cache, if it's there.
+    pub fn take(&mut self, source: &Source) -> Option<Result<Loaded, String>> {
+        self.cache.remove(&source.to_string())
+    }
+
+    /// Starts decoding whichever of `sources` aren't decoded yet, and forgets every other image.
+    pub fn prefetch(&mut self, sources: &[&Source]) {
+        self.wanted = sources.iter().map(|source| source.to_string()).collect();
+        self.cache.retain(|key, _| self.wanted.contains(key));
+        for source in sources {
+            let key = source.to_string();
+            if self.cache.contains_key(&key) || self.pending.contains(&key) {
+                continue;
+            }
+            if self.requests.send((*source).clone()).is_ok() {
+                self.pending.insert(key);
+            }
+        }
+    }
+
+    /// Files away an image the decoding thread finished, unless it's no longer wanted.
+    pub fn receive(&mut self, decoded: Decoded) {
+        self.pending.remove(&decoded.key);
+        if self.wanted.contains(&decoded.key) {
+            self.cache.insert(decoded.key, decoded.result);
+        }
+    }
+}
@@ -0,0 +1,1945 @@
+use std::{
+    cmp::max,
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use clap::ArgEnum;
+use image::{imageops, RgbaImage};
+use serde::Deserialize;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use crate::Color;
+
+/// Images at least this many times taller than wide are scrolled through rather than shrunk
+/// until they fit.
+const TALL_RATIO: f32 = 3.0;
+const CHECKER_SIZE: u32 = 8;
+const CHECKER_LIGHT: [u8; 3] = [0xcc, 0xcc, 0xcc];
+const CHECKER_DARK: [u8; 3] = [0x99, 0x99, 0x99];
+/// How many window pixels an image pixel has to take up, across and down, for the pixel grid to
+/// show up.
+const PIXEL_GRID_ZOOM: f32 = 8.0;
+const PIXEL_GRID_COLOR: [u8; 3] = [0x80, 0x80, 0x80];
+const PIXEL_GRID_ALPHA: u8 = 0x60;
+pub const MIN_ZOOM: f32 = 0.1;
+pub const MAX_ZOOM: f32 = 20.0;
+/// How long zooming and panning by a step takes to glide there.
+const GLIDE_TIME: Duration = Duration::from_millis(150);
+
+/// How an image is scaled to the window it's displayed in.
+#[derive(ArgEnum, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FitMode {
+    /// Shrink the image by a whole factor until it fits entirely.
+    #[default]
+    Contain,
+    /// Show one image pixel per window pixel.
+    Actual,
+    /// Cover the whole window, cropping whatever overflows.
+    Fill,
+    /// Match the width of the window, scrolling through whatever is taller than it.
+    Width,
+    /// Match the height of the window.
+    Height,
+}
+
+impl FitMode {
+    /// The zoom level at which an image of `image_size` is fit to a window of `size` in this
+    /// mode, on a screen with `scale` window pixels per logical pixel. Actual size is one image
+    /// pixel per logical pixel, so that images look as big on a HiDPI screen as on any other.
+    pub fn zoom(self, size: PhysicalSize<u32>, image_size: (u32, u32), scale: f32) -> f32 {
+        let (width, height) = image_size;
+        let horz_ratio = size.width as f32 / width as f32;
+        let vert_ratio = size.height as f32 / height as f32;
+        let zoom = match self {
+            FitMode::Contain => {
+                let scaled = |side: u32| (side as f32 * scale).ceil() as u32;
+                let horz_scale = calc_scale(size.width, scaled(width));
+                let vert_scale = calc_scale(size.height, scaled(height));
+                scale / max(horz_scale, vert_scale) as f32
+            }
+            FitMode::Actual => scale,
+            FitMode::Fill => horz_ratio.max(vert_ratio),
+            FitMode::Width => horz_ratio,
+            FitMode::Height => vert_ratio,
+        };
+        zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+    }
+
+    /// The mode an image of `image_size` is actually fit in: long strips are fit to the width
+    /// when `tall` is set, or when they'd otherwise be shrunk to fit entirely.
+    pub fn for_image(self, tall: bool, image_size: (u32, u32)) -> Self {
+        let (width, height) = image_size;
+        let is_strip = height as f32 >= width as f32 * TALL_RATIO;
+        if tall || (self == FitMode::Contain && is_strip) {
+            FitMode::Width
+        } else {
+            self
+        }
+    }
+
+    /// The mode after this one when cycling through all of them.
+    pub fn next(self) -> Self {
+        match self {
+            FitMode::Contain => FitMode::Actual,
+            FitMode::Actual => FitMode::Fill,
+            FitMode::Fill => FitMode::Width,
+            FitMode::Width => FitMode::Height,
+            FitMode::Height => FitMode::Contain,
+        }
+    }
+}
+
+/// How the image is sampled when it's scaled.
+#[derive(ArgEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Filter {
+    /// Nearest at whole-number zooms, where every image pixel becomes a square, linear at others.
+    #[default]
+    Auto,
+    /// Each window pixel takes the color of the closest image pixel, keeping edges crisp.
+    Nearest,
+    /// Colors are interpolated between the four closest image pixels, smoothing edges.
+    Linear,
+}
+
+impl Filter {
+    /// The filter after this one when cycling through all of them.
+    pub fn next(self) -> Self {
+        match self {
+            Filter::Auto => Filter::Nearest,
+            Filter::Nearest => Filter::Linear,
+            Filter::Linear => Filter::Auto,
+        }
+    }
+
+    /// Whether to interpolate at `zoom`.
+    pub fn is_linear(self, zoom: f32) -> bool {
+        match self {
+            Filter::Auto => (zoom - zoom.round()).abs() > 1e-3,
+            Filter::Nearest => false,
+            Filter::Linear => true,
+        }
+    }
+}
+
+/// What shows through the transparent parts of an image.
+#[derive(Debug, Clone, Copy)]
+pub enum Backdrop {
+    Checkerboard,
+    Solid(Color),
+}
+
+/// Changes made to a decoded frame before it's displayed. None of them touch the frame itself,
+/// so they can all be undone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// Clockwise quarter turns.
+    pub rotation: u8,
+    /// Mirror left and right, after rotating.
+    pub flip_horizontal: bool,
+    /// Mirror top and bottom, after rotating.
+    pub flip_vertical: bool,
+    /// Added to every color channel, from -1 (all black) to 1 (all white).
+    pub brightness: f32,
+    /// How far color channels are pushed away from mid-gray, 1 leaving them as they are.
+    pub contrast: f32,
+    /// Turn every color channel into its opposite, after adjusting brightness and contrast.
+    pub invert: bool,
+    /// Show only the luminance, after adjusting brightness and contrast.
+    pub grayscale: bool,
+    /// The part of the frame to keep, before anything else is done to it.
+    pub crop: Option<Crop>,
+}
+
+/// A rectangle of a frame, in its pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Crop {
+    /// The same part of a frame that's been scaled by `ratio`.
+    pub fn scaled(self, ratio: f32) -> Self {
+        let scale = |value: u32| (value as f32 * ratio).round() as u32;
+        Self {
+            x: scale(self.x),
+            y: scale(self.y),
+            width: scale(self.width).max(1),
+            height: scale(self.height).max(1),
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            rotation: 0,
+            flip_horizontal: false,
+            flip_vertical: false,
+            brightness: 0.0,
+            contrast: 1.0,
+            invert: false,
+            grayscale: false,
+            crop: None,
+        }
+    }
+}
+
+impl Transform {
+    /// Maps every possible channel value through the brightness and contrast adjustment, and
+    /// the inversion.
+    fn color_table(&self) -> [u8; 256] {
+        let mut table = [0; 256];
+        for (value, entry) in table.iter_mut().enumerate() {
+            let value = value as f32 / 255.0;
+            let adjusted = (value - 0.5) * self.contrast + 0.5 + self.brightness;
+            *entry = (adjusted.clamp(0.0, 1.0) * 255.0).round() as u8;
+            if self.invert {
+                *entry = 255 - *entry;
+            }
+        }
+        table
+    }
+
+    /// Whether every pixel stays where it was, so that positions in the displayed image are
+    /// positions in the original.
+    pub fn keeps_geometry(&self) -> bool {
+        self.rotation == 0 && !self.flip_horizontal && !self.flip_vertical && self.crop.is_none()
+    }
+
+    /// Maps `point` in the displayed image back to the cropped frame it came from, which is
+    /// `size` pixels big.
+    fn unmap(&self, point: (f32, f32), size: (u32, u32)) -> (f32, f32) {
+        let (width, height) = (size.0 as f32, size.1 as f32);
+        // Flips happen after rotating, so they're undone in the rotated size.
+        let (rotated_width, rotated_height) = if self.rotation % 2 == 1 {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let (mut x, mut y) = point;
+        if self.flip_vertical {
+            y = rotated_height - y;
+        }
+        if self.flip_horizontal {
+            x = rotated_width - x;
+        }
+        match self.rotation {
+            1 => (y, height - x),
+            2 => (width - x, height - y),
+            3 => (width - y, x),
+            _ => (x, y),
+        }
+    }
+
+    /// Maps `point` in the displayed image back to the `frame_size` frame it came from.
+    pub fn frame_point(&self, point: (f32, f32), frame_size: (u32, u32)) -> (f32, f32) {
+        match self.crop {
+            Some(crop) => {
+                let (x, y) = self.unmap(point, (crop.width, crop.height));
+                (x + crop.x as f32, y + crop.y as f32)
+            }
+            None => self.unmap(point, frame_size),
+        }
+    }
+
+    /// The crop that narrows down what's displayed to the rectangle between the corners
+    /// `selection`, in displayed pixels, of a `frame_size` frame. `None` if nothing of the
+    /// image is inside.
+    pub fn crop_to(
+        &self,
+        selection: ((f32, f32), (f32, f32)),
+        frame_size: (u32, u32),
+    ) -> Option<Crop> {
+        let current = self.crop.unwrap_or(Crop {
+            x: 0,
+            y: 0,
+            width: frame_size.0,
+            height: frame_size.1,
+        });
+        let size = (current.width, current.height);
+        let (a, b) = (self.unmap(selection.0, size), self.unmap(selection.1, size));
+        let bounds = |a: f32, b: f32, limit: u32| {
+            let start = a.min(b).floor().clamp(0.0, limit as f32) as u32;
+            let end = a.max(b).ceil().clamp(0.0, limit as f32) as u32;
+            (start, end - start)
+        };
+        let (x, width) = bounds(a.0, b.0, current.width);
+        let (y, height) = bounds(a.1, b.1, current.height);
+        (width > 0 && height > 0).then_some(Crop {
+            x: current.x + x,
+            y: current.y + y,
+            width,
+            height,
+        })
+    }
+
+    fn adjusts_colors(&self) -> bool {
+        self.brightness != 0.0 || self.contrast != 1.0 || self.invert
+    }
+}
+
+/// Turns a decoded frame into the opaque image that gets drawn: with `transform` applied and any
+/// transparency flattened onto `backdrop`.
+pub fn prepare(frame: &RgbaImage, transform: Transform, backdrop: Backdrop) -> RgbaImage {
+    let image = apply(frame, transform);
+    let (width, height) = image.dimensions();
+    let flattened = match backdrop {
+        Backdrop::Checkerboard => composite_over_checkerboard(image.as_raw(), width, height),
+        Backdrop::Solid(Color(color)) => composite(image.as_raw(), width, height, |_, _| color),
+    };
+    RgbaImage::from_raw(width, height, flattened).expect("compositing keeps the image size")
+}
+
+/// Rotates, flips and adjusts the colors of `frame` as `transform` says, leaving its
+/// transparency alone.
+pub fn apply(frame: &RgbaImage, transform: Transform) -> RgbaImage {
+    let cropped = transform
+        .crop
+        .map(|crop| imageops::crop_imm(frame, crop.x, crop.y, crop.width, crop.height).to_image());
+    let mut image = rotate(cropped.as_ref().unwrap_or(frame), transform.rotation);
+    if transform.flip_horizontal {
+        imageops::flip_horizontal_in_place(&mut image);
+    }
+    if transform.flip_vertical {
+        imageops::flip_vertical_in_place(&mut image);
+    }
+    if transform.adjusts_colors() {
+        let table = transform.color_table();
+        for pixel in image.pixels_mut() {
+            for channel in &mut pixel.0[..3] {
+                *channel = table[usize::from(*channel)];
+            }
+        }
+    }
+    if transform.grayscale {
+        for pixel in image.pixels_mut() {
+            let [r, g, b, _] = pixel.0;
+            // Rec. 709 luma weights, the same ones `image` uses for its own conversions.
+            let luma = (2126 * u32::from(r) + 7152 * u32::from(g) + 722 * u32::from(b)) / 10000;
+            pixel.0[..3].fill(luma as u8);
+        }
+    }
+    image
+}
+
+/// Alpha-blends `rgba`, a `width` x `height` image, over a light/dark gray checkerboard so that
+/// transparent areas are recognizable. The result is fully opaque.
+fn composite_over_checkerboard(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    composite(rgba, width, height, |x, y| {
+        if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) {
+            CHECKER_LIGHT
+        } else {
+            CHECKER_DARK
+        }
+    })
+}
+
+/// Alpha-blends `rgba`, a `width` x `height` image, over the color `backdrop` returns for each
+/// pixel position. The result is fully opaque.
+fn composite(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    backdrop: impl Fn(u32, u32) -> [u8; 3],
+) -> Vec<u8> {
+    debug_assert_eq!(rgba.len(), width as usize * height as usize * 4);
+
+    let mut out = Vec::with_capacity(rgba.len());
+    for (i, pixel) in rgba.chunks_exact(4).enumerate() {
+        let alpha = u32::from(pixel[3]);
+        let back = backdrop(i as u32 % width, i as u32 / width);
+        for channel in 0..3 {
+            let blended = (u32::from(pixel[channel]) * alpha
+                + u32::from(back[channel]) * (255 - alpha))
+                / 255;
+            out.push(blended as u8);
+        }
+        out.push(0xff);
+    }
+    out
+}
+
+/// Turns `image` clockwise by `quarter_turns` multiples of 90°.
+fn rotate(image: &RgbaImage, quarter_turns: u8) -> RgbaImage {
+    match quarter_turns % 4 {
+        1 => imageops::rotate90(image),
+        2 => imageops::rotate180(image),
+        3 => imageops::rotate270(image),
+        _ => image.clone(),
+    }
+}
+
+/// The whole factor `current_size` has to be divided by to fit in `max_size`.
+fn calc_scale(max_size: u32, current_size: u32) -> u32 {
+    if max_size >= current_size {
+        1
+    } else {
+        ((current_size as f32) / (max_size as f32)).ceil() as u32
+    }
+}
+
+/// The inner size of a window showing all of an image of `image_size` at `zoom` with `padding`
+/// around it, as far as `max_size` allows.
+/// Rounding up keeps a contained image from needing a smaller scale once it's in the window.
+pub fn window_size(
+    image_size: (u32, u32),
+    zoom: f32,
+    max_size: PhysicalSize<u32>,
+    padding: u32,
+) -> PhysicalSize<u32> {
+    let side = |length: u32| {
+        ((length as f32 * zoom).ceil() as u32).saturating_add(padding.saturating_mul(2))
+    };
+    PhysicalSize::new(
+        side(image_size.0).clamp(1, max_size.width),
+        side(image_size.1).clamp(1, max_size.height),
+    )
+}
+
+/// What's left of `size` inside a margin of `padding`. The margin takes up no more than a quarter
+/// of either side, so that a small window still has room for the image.
+pub fn inset(size: PhysicalSize<u32>, padding: u32) -> PhysicalSize<u32> {
+    let padding = padding.min(size.width.min(size.height) / 4);
+    PhysicalSize::new(
+        (size.width - 2 * padding).max(1),
+        (size.height - 2 * padding).max(1),
+    )
+}
+
+/// How the image is placed in the window.
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    /// Window pixels per image pixel.
+    pub zoom: f32,
+    /// Displacement of the image center from the window center, in window pixels.
+    pub offset: (f32, f32),
+    /// Whether the zoom still comes from the fit mode, so that it follows the window size.
+    /// Zooming by hand turns this off.
+    pub fitted: bool,
+    /// The margin kept around the image, in window pixels.
+    pub padding: u32,
+    /// Window pixels per logical pixel, from the scale factor of the monitor.
+    pub scale: f32,
+    /// Where the view is gliding to its zoom and offset from.
+    pub glide: Option<Glide>,
+    /// Whether the image is distorted to fill the padded area instead, which leaves nothing to
+    /// zoom or pan.
+    pub stretch: bool,
+}
+
+/// The start of an animated change of the view.
+#[derive(Debug, Clone, Copy)]
+pub struct Glide {
+    zoom: f32,
+    offset: (f32, f32),
+    start: Instant,
+}
+
+impl View {
+    pub fn new(zoom: f32, padding: u32, scale: f32) -> Self {
+        Self {
+            zoom,
+            offset: (0.0, 0.0),
+            fitted: true,
+            padding,
+            scale,
+            glide: None,
+            stretch: false,
+        }
+    }
+
+    /// Makes the next change to the zoom or offset glide there from where the view is shown
+    /// now, instead of jumping.
+    pub fn glide(&mut self) {
+        let now = Instant::now();
+        let shown = self.shown(now);
+        self.glide = Some(Glide {
+            zoom: shown.zoom,
+            offset: shown.offset,
+            start: now,
+        });
+    }
+
+    /// The view as it's shown at `now`: part of the way there while it's gliding.
+    pub fn shown(&self, now: Instant) -> View {
+        let glide = match self.glide {
+            Some(glide) => glide,
+            None => return *self,
+        };
+        let progress =
+            now.saturating_duration_since(glide.start).as_secs_f32() / GLIDE_TIME.as_secs_f32();
+        // Eased out, so that it slows down as it gets there.
+        let eased = 1.0 - (1.0 - progress.min(1.0)).powi(3);
+        let lerp = |from: f32, to: f32| from + (to - from) * eased;
+        View {
+            zoom: lerp(glide.zoom, self.zoom),
+            offset: (
+                lerp(glide.offset.0, self.offset.0),
+                lerp(glide.offset.1, self.offset.1),
+            ),
+            glide: None,
+            ..*self
+        }
+    }
+
+    /// Forgets the glide once it's over by `now`.
+    pub fn settle(&mut self, now: Instant) {
+        if self
+            .glide
+            .is_some_and(|glide| now >= glide.start + GLIDE_TIME)
+        {
+            self.glide = None;
+        }
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the point at the window center in place.
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.zoom_at(factor, (0.0, 0.0));
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the point `anchor` pixels away from the window
+    /// center in place.
+    pub fn zoom_at(&mut self, factor: f32, anchor: (f32, f32)) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let ratio = zoom / self.zoom;
+        self.offset = (
+            anchor.0 - (anchor.0 - self.offset.0) * ratio,
+            anchor.1 - (anchor.1 - self.offset.1) * ratio,
+        );
+        self.zoom = zoom;
+        self.fitted = false;
+    }
+
+    /// Fits `image` inside the padding of a window of `frame_size` in mode `fit`, undoing any
+    /// zooming and panning. An image fit to the width starts out scrolled to the top, where it's
+    /// read from.
+    pub fn refit(&mut self, fit: FitMode, frame_size: PhysicalSize<u32>, image: &RgbaImage) {
+        let zoom = fit.zoom(self.area(frame_size), image.dimensions(), self.scale);
+        *self = Self {
+            stretch: self.stretch,
+            ..Self::new(zoom, self.padding, self.scale)
+        };
+        if fit == FitMode::Width {
+            self.pan(0.0, f32::MAX);
+            self.clamp(frame_size, image);
+        }
+    }
+
+    /// Zooms to `zoom`, as the user sees it, and pans by `pan` from the middle, as --zoom and
+    /// --pan ask for. Either is brought back into range with a warning if it's too far out.
+    pub fn start_at(
+        &mut self,
+        zoom: Option<f32>,
+        pan: Option<(f32, f32)>,
+        frame_size: PhysicalSize<u32>,
+        image: &RgbaImage,
+    ) {
+        if let Some(zoom) = zoom {
+            let wanted = zoom * self.scale;
+            self.zoom = wanted.clamp(MIN_ZOOM, MAX_ZOOM);
+            self.fitted = false;
+            if self.zoom != wanted {
+                eprintln!(
+                    "Zoom {} is out of range, using {}",
+                    zoom,
+                    self.perceived_zoom()
+                );
+            }
+        }
+        self.offset = pan.unwrap_or((0.0, 0.0));
+        self.clamp(frame_size, image);
+        if let Some((x, y)) = pan.filter(|&pan| pan != self.offset) {
+            eprintln!(
+                "Can't pan by {},{} at this zoom, panning by {},{}",
+                x, y, self.offset.0, self.offset.1
+            );
+        }
+    }
+
+    /// The zoom as the user sees it, 1 being actual size.
+    pub fn perceived_zoom(&self) -> f32 {
+        self.zoom / self.scale
+    }
+
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.offset = (self.offset.0 + dx, self.offset.1 + dy);
+    }
+
+    /// The part of a window of `frame_size` inside the padding, where the image is shown.
+    pub fn area(&self, frame_size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+        inset(frame_size, self.padding)
+    }
+
+    /// Whether all of `image` is visible in a window of `frame_size`.
+    pub fn fits(&self, frame_size: PhysicalSize<u32>, image: &RgbaImage) -> bool {
+        self.fits_horizontally(frame_size, image)
+            && (self.stretch
+                || image.height() as f32 * self.zoom <= self.area(frame_size).height as f32)
+    }
+
+    /// Pans so that the pixel `point` of `image` is in the middle of a window of `frame_size`,
+    /// or as close to it as the edges of the image allow.
+    pub fn center_on(
+        &mut self,
+        point: (f32, f32),
+        frame_size: PhysicalSize<u32>,
+        image: &RgbaImage,
+    ) {
+        self.offset = (
+            (image.width() as f32 / 2.0 - point.0) * self.zoom,
+            (image.height() as f32 / 2.0 - point.1) * self.zoom,
+        );
+        self.clamp(frame_size, image);
+    }
+
+    /// Whether the whole width of `image` is visible in a window of `frame_size`.
+    pub fn fits_horizontally(&self, frame_size: PhysicalSize<u32>, image: &RgbaImage) -> bool {
+        self.stretch || image.width() as f32 * self.zoom <= self.area(frame_size).width as f32
+    }
+
+    /// Limits the offset so that the image never leaves the padded area of the window: an image
+    /// larger than it can be panned until its edge meets the padding, a smaller one stays
+    /// centered.
+    pub fn clamp(&mut self, frame_size: PhysicalSize<u32>, image: &RgbaImage) {
+        let frame_size = self.area(frame_size);
+        let limit =
+            |frame: u32, image: u32| ((image as f32 * self.zoom - frame as f32) / 2.0).max(0.0);
+        let limit_x = limit(frame_size.width, image.width());
+        let limit_y = limit(frame_size.height, image.height());
+        self.offset = (
+            self.offset.0.clamp(-limit_x, limit_x),
+            self.offset.1.clamp(-limit_y, limit_y),
+        );
+    }
+}
+
+/// Where the image ends up in the window, in window pixels. The corner may be outside of the
+/// window, or the rectangle bigger than it.
+#[derive(Debug, Clone, Copy)]
+pub struct Placement {
+    left: f32,
+    top: f32,
+    width: f32,
+    height: f32,
+    /// Window pixels per image pixel, across and down, which only differ when stretched.
+    zoom: (f32, f32),
+}
+
+impl Placement {
+    /// Centers `image`, scaled by the zoom of `view`, in a `width` x `height` window and moves it
+    /// by the offset of `view`. A stretched view spreads it over the padded area instead.
+    pub fn new(width: u32, height: u32, image: &RgbaImage, view: &View) -> Self {
+        let scaled = if view.stretch {
+            let area = view.area(PhysicalSize::new(width, height));
+            (area.width as f32, area.height as f32)
+        } else {
+            (
+                image.width() as f32 * view.zoom,
+                image.height() as f32 * view.zoom,
+            )
+        };
+        let offset = if view.stretch {
+            (0.0, 0.0)
+        } else {
+            view.offset
+        };
+        Self {
+            left: (width as f32 - scaled.0) / 2.0 + offset.0,
+            top: (height as f32 - scaled.1) / 2.0 + offset.1,
+            width: scaled.0,
+            height: scaled.1,
+            zoom: (
+                scaled.0 / image.width() as f32,
+                scaled.1 / image.height() as f32,
+            ),
+        }
+    }
+
+    /// Where the window pixel `position` is in the image, in image pixels.
+    pub fn image_point(&self, position: PhysicalPosition<f64>) -> (f32, f32) {
+        (
+            (position.x as f32 - self.left) / self.zoom.0,
+            (position.y as f32 - self.top) / self.zoom.1,
+        )
+    }
+
+    /// The window pixel the image pixel (`x`, `y`) is drawn at, rounded to the closest one.
+    pub fn window_point(&self, x: f32, y: f32) -> (i64, i64) {
+        (
+            (self.left + x * self.zoom.0).round() as i64,
+            (self.top + y * self.zoom.1).round() as i64,
+        )
+    }
+
+    /// The window columns and rows the image is drawn in: those whose centers it covers, inside
+    /// the padding of `view` in a `width` x `height` window.
+    fn visible(&self, width: u32, height: u32, view: &View) -> (Range<usize>, Range<usize>) {
+        let area = view.area(PhysicalSize::new(width, height));
+        let margin = ((width - area.width) / 2, (height - area.height) / 2);
+        let clip = |range: Range<usize>, margin: u32, size: u32| {
+            range.start.max(margin as usize)..range.end.min((size - margin) as usize)
+        };
+        (
+            clip(Self::covered(self.left, self.width, width), margin.0, width),
+            clip(
+                Self::covered(self.top, self.height, height),
+                margin.1,
+                height,
+            ),
+        )
+    }
+
+    /// The range of window pixels along one axis whose centers the image covers, clipped to
+    /// `0..size`.
+    fn covered(start: f32, length: f32, size: u32) -> Range<usize> {
+        let first = (start - 0.5).ceil().clamp(0.0, size as f32);
+        let end = (start + length - 0.5).ceil().clamp(first, size as f32);
+        first as usize..end as usize
+    }
+}
+
+/// Draws `image` into `frame`, a `width` x `height` RGBA buffer, placed according to `view`.
+/// Anything not covered by the image, and the padding, is painted `background`.
+pub fn draw(
+    frame: &mut [u8],
+    width: u32,
+    height: u32,
+    image: &RgbaImage,
+    view: &View,
+    background: Color,
+    filter: Filter,
+) {
+    draw_columns(
+        frame,
+        (width, height),
+        0..width,
+        image,
+        view,
+        background,
+        filter,
+    );
+}
+
+/// Like [`draw`], but only into the window columns `only`, leaving the rest of `frame` as it
+/// was.
+pub fn draw_columns(
+    frame: &mut [u8],
+    (width, height): (u32, u32),
+    only: Range<u32>,
+    image: &RgbaImage,
+    view: &View,
+    background: Color,
+    filter: Filter,
+) {
+    let only = only.start.min(width) as usize..only.end.min(width) as usize;
+    let background = background.to_rgba();
+    for row in frame.chunks_exact_mut(width as usize * 4) {
+        for pixel in row[only.start * 4..only.end * 4].chunks_exact_mut(4) {
+            pixel.copy_from_slice(&background);
+        }
+    }
+
+    let placement = Placement::new(width, height, image, view);
+    let (columns, rows) = placement.visible(width, height, view);
+    let columns = columns.start.max(only.start)..columns.end.min(only.end);
+    let row_length = width as usize * 4;
+    let linear = filter.is_linear(placement.zoom.0.min(placement.zoom.1));
+    for y in rows {
+        let src_y = (y as f32 + 0.5 - placement.top) / placement.zoom.1;
+        let row = &mut frame[y * row_length..(y + 1) * row_length];
+        for x in columns.clone() {
+            let src_x = (x as f32 + 0.5 - placement.left) / placement.zoom.0;
+            let pixel = if linear {
+                sample_linear(image, src_x, src_y)
+            } else {
+                // Rounding can land a hair past the last pixel at the far edges.
+                image
+                    .get_pixel(
+                        (src_x as u32).min(image.width() - 1),
+                        (src_y as u32).min(image.height() - 1),
+                    )
+                    .0
+            };
+            row[x * 4..x * 4 + 4].copy_from_slice(&pixel);
+        }
+    }
+}
+
+/// Draws faint lines between the pixels of `image` over where [`draw_columns`] put it in the
+/// window columns `only`, once they're blown up enough for every pixel to be told apart. Each
+/// line takes up the first window row or column of a pixel.
+pub fn draw_pixel_grid(
+    frame: &mut [u8],
+    (width, height): (u32, u32),
+    only: Range<u32>,
+    image: &RgbaImage,
+    view: &View,
+) {
+    let placement = Placement::new(width, height, image, view);
+    if placement.zoom.0.min(placement.zoom.1) < PIXEL_GRID_ZOOM {
+        return;
+    }
+    let (columns, rows) = placement.visible(width, height, view);
+    let columns = columns.start.max(only.start as usize)..columns.end.min(only.end as usize);
+    // Where the pixels after the first one start, along one axis.
+    let lines = |start: f32, zoom: f32, count: u32, within: &Range<usize>| -> Vec<usize> {
+        (1..count)
+            .map(|i| (start + i as f32 * zoom - 0.5).ceil())
+            .filter(|&line| line >= within.start as f32 && line < within.end as f32)
+            .map(|line| line as usize)
+            .collect()
+    };
+    let line_columns = lines(placement.left, placement.zoom.0, image.width(), &columns);
+    let line_rows = lines(placement.top, placement.zoom.1, image.height(), &rows);
+
+    let alpha = u32::from(PIXEL_GRID_ALPHA);
+    let mut blend = |x: usize, y: usize| {
+        let at = (y * width as usize + x) * 4;
+        for (channel, line) in frame[at..at + 3].iter_mut().zip(PIXEL_GRID_COLOR) {
+            *channel =
+                ((u32::from(*channel) * (255 - alpha) + u32::from(line) * alpha) / 255) as u8;
+        }
+    };
+    for &x in &line_columns {
+        for y in rows.clone() {
+            blend(x, y);
+        }
+    }
+    for &y in &line_rows {
+        // Where the lines cross was blended already.
+        for x in columns
+            .clone()
+            .filter(|x| line_columns.binary_search(x).is_err())
+        {
+            blend(x, y);
+        }
+    }
+}
+
+/// Interpolates the color of `image` at (`x`, `y`) from the four pixels whose centers are
+/// closest. Past the outer pixel centers, the edge pixels are stretched.
+fn sample_linear(image: &RgbaImage, x: f32, y: f32) -> [u8; 4] {
+    let (x, y) = (x - 0.5, y - 0.5);
+    let clamp = |value: f32, size: u32| value.clamp(0.0, (size - 1) as f32);
+    let (x, y) = (clamp(x, image.width()), clamp(y, image.height()));
+    let (left, top) = (x.floor() as u32, y.floor() as u32);
+    let right = (left + 1).min(image.width() - 1);
+    let bottom = (top + 1).min(image.height() - 1);
+    let (dx, dy) = (x - left as f32, y - top as f32);
+
+    let corners = [
+        image.get_pixel(left, top).0,
+        image.get_pixel(right, top).0,
+        image.get_pixel(left, bottom).0,
+        image.get_pixel(right, bottom).0,
+    ];
+    let weights = [
+        (1.0 - dx) * (1.0 - dy),
+        dx * (1.0 - dy),
+        (1.0 - dx) * dy,
+        dx * dy,
+    ];
+    let mut pixel = [0; 4];
+    for (channel, value) in pixel.iter_mut().enumerate() {
+        let sum: f32 = corners
+            .iter()
+            .zip(weights)
+            .map(|(corner, weight)| f32::from(corner[channel]) * weight)
+            .sum();
+        *value = sum.round() as u8;
+    }
+    pixel
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    const WHITE: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+    const BLUE: Color = Color([0, 0, 0xff]);
+
+    fn size(width: u32, height: u32) -> PhysicalSize<u32> {
+        PhysicalSize::new(width, height)
+    }
+
+    fn solid(width: u32, height: u32, pixel: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba(pixel))
+    }
+
+    /// An opaque image whose red channel is the column of a pixel and whose green is its row, so
+    /// that every pixel can be traced back to where it came from.
+    fn numbered(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| Rgba([x as u8, y as u8, 0, 0xff]))
+    }
+
+    fn pixel_at(frame: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let at = (y as usize * width as usize + x as usize) * 4;
+        frame[at..at + 4].try_into().unwrap()
+    }
+
+    fn drawn(width: u32, height: u32, image: &RgbaImage, view: &View) -> Vec<u8> {
+        let mut frame = vec![0; width as usize * height as usize * 4];
+        draw(
+            &mut frame,
+            width,
+            height,
+            image,
+            view,
+            BLUE,
+            Filter::Nearest,
+        );
+        frame
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{} isn't close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn calc_scale_keeps_what_fits() {
+        assert_eq!(calc_scale(100, 50), 1);
+        assert_eq!(calc_scale(100, 100), 1);
+    }
+
+    #[test]
+    fn calc_scale_shrinks_by_whole_factors() {
+        assert_eq!(calc_scale(100, 101), 2);
+        assert_eq!(calc_scale(100, 200), 2);
+        assert_eq!(calc_scale(100, 201), 3);
+        assert_eq!(calc_scale(1, 100_000), 100_000);
+    }
+
+    #[test]
+    fn calc_scale_handles_zero_sizes() {
+        assert_eq!(calc_scale(100, 0), 1);
+        assert_eq!(calc_scale(0, 0), 1);
+        assert_eq!(calc_scale(0, 10), u32::MAX);
+    }
+
+    #[test]
+    fn contain_never_enlarges() {
+        assert_eq!(FitMode::Contain.zoom(size(800, 600), (400, 300), 1.0), 1.0);
+        assert_eq!(FitMode::Contain.zoom(size(800, 600), (1, 1), 1.0), 1.0);
+    }
+
+    #[test]
+    fn contain_shrinks_by_the_larger_factor() {
+        assert_eq!(FitMode::Contain.zoom(size(800, 600), (1000, 500), 1.0), 0.5);
+        assert_close(
+            FitMode::Contain.zoom(size(800, 600), (400, 1300), 1.0),
+            1.0 / 3.0,
+        );
+    }
+
+    #[test]
+    fn contain_counts_logical_pixels_on_hidpi_screens() {
+        assert_eq!(FitMode::Contain.zoom(size(800, 600), (400, 300), 2.0), 2.0);
+        assert_eq!(FitMode::Contain.zoom(size(800, 600), (500, 300), 2.0), 1.0);
+    }
+
+    #[test]
+    fn actual_is_one_image_pixel_per_logical_pixel() {
+        assert_eq!(FitMode::Actual.zoom(size(800, 600), (4000, 10), 1.0), 1.0);
+        assert_eq!(FitMode::Actual.zoom(size(800, 600), (4000, 10), 1.5), 1.5);
+    }
+
+    #[test]
+    fn fill_covers_both_sides() {
+        assert_eq!(FitMode::Fill.zoom(size(800, 600), (400, 400), 1.0), 2.0);
+        assert_eq!(FitMode::Fill.zoom(size(800, 600), (1600, 400), 1.0), 1.5);
+    }
+
+    #[test]
+    fn width_and_height_match_one_side() {
+        assert_eq!(FitMode::Width.zoom(size(800, 600), (1600, 100), 1.0), 0.5);
+        assert_eq!(FitMode::Width.zoom(size(800, 600), (400, 10_000), 1.0), 2.0);
+        assert_eq!(FitMode::Height.zoom(size(800, 600), (100, 1200), 1.0), 0.5);
+        assert_eq!(
+            FitMode::Height.zoom(size(800, 600), (10_000, 300), 1.0),
+            2.0
+        );
+    }
+
+    #[test]
+    fn fit_zoom_stays_in_range_for_extreme_aspect_ratios() {
+        assert_eq!(FitMode::Fill.zoom(size(800, 600), (1, 1), 1.0), MAX_ZOOM);
+        assert_eq!(
+            FitMode::Width.zoom(size(800, 600), (100_000, 1), 1.0),
+            MIN_ZOOM
+        );
+        assert_eq!(
+            FitMode::Height.zoom(size(800, 600), (1, 100_000), 1.0),
+            MIN_ZOOM
+        );
+        assert_eq!(
+            FitMode::Contain.zoom(size(800, 600), (1, 100_000), 1.0),
+            MIN_ZOOM
+        );
+        assert_eq!(FitMode::Actual.zoom(size(1, 1), (1, 1), 100.0), MAX_ZOOM);
+    }
+
+    #[test]
+    fn fit_zoom_is_in_range_for_empty_images() {
+        for fit in [
+            FitMode::Contain,
+            FitMode::Actual,
+            FitMode::Fill,
+            FitMode::Width,
+            FitMode::Height,
+        ] {
+            let zoom = fit.zoom(size(800, 600), (0, 0), 1.0);
+            assert!((MIN_ZOOM..=MAX_ZOOM).contains(&zoom), "{:?}: {}", fit, zoom);
+        }
+    }
+
+    #[test]
+    fn strips_are_fit_to_the_width() {
+        assert_eq!(
+            FitMode::Contain.for_image(false, (100, 300)),
+            FitMode::Width
+        );
+        assert_eq!(
+            FitMode::Contain.for_image(false, (1, 100_000)),
+            FitMode::Width
+        );
+        assert_eq!(
+            FitMode::Contain.for_image(false, (100, 299)),
+            FitMode::Contain
+        );
+        assert_eq!(
+            FitMode::Contain.for_image(false, (100_000, 1)),
+            FitMode::Contain
+        );
+    }
+
+    #[test]
+    fn only_contain_turns_into_width_by_itself() {
+        for fit in [FitMode::Actual, FitMode::Fill, FitMode::Height] {
+            assert_eq!(fit.for_image(false, (100, 1000)), fit);
+        }
+    }
+
+    #[test]
+    fn tall_fits_everything_to_the_width() {
+        assert_eq!(FitMode::Contain.for_image(true, (400, 300)), FitMode::Width);
+        assert_eq!(FitMode::Actual.for_image(true, (400, 300)), FitMode::Width);
+        assert_eq!(FitMode::Height.for_image(true, (400, 300)), FitMode::Width);
+    }
+
+    #[test]
+    fn fit_modes_cycle_through_all_of_them() {
+        let mut fit = FitMode::default();
+        let mut seen = Vec::new();
+        for _ in 0..5 {
+            assert!(!seen.contains(&fit));
+            seen.push(fit);
+            fit = fit.next();
+        }
+        assert_eq!(fit, FitMode::default());
+    }
+
+    #[test]
+    fn filters_cycle_through_all_of_them() {
+        let filter = Filter::default();
+        assert_ne!(filter.next(), filter);
+        assert_ne!(filter.next().next(), filter);
+        assert_eq!(filter.next().next().next(), filter);
+    }
+
+    #[test]
+    fn auto_filter_interpolates_only_between_whole_zooms() {
+        assert!(!Filter::Auto.is_linear(1.0));
+        assert!(!Filter::Auto.is_linear(3.0));
+        assert!(!Filter::Auto.is_linear(2.0005));
+        assert!(Filter::Auto.is_linear(1.5));
+        assert!(Filter::Auto.is_linear(0.5));
+        assert!(!Filter::Nearest.is_linear(1.5));
+        assert!(Filter::Linear.is_linear(1.0));
+    }
+
+    #[test]
+    fn inset_takes_padding_off_every_side() {
+        assert_eq!(inset(size(800, 600), 10), size(780, 580));
+        assert_eq!(inset(size(800, 600), 0), size(800, 600));
+    }
+
+    #[test]
+    fn inset_padding_takes_at_most_a_quarter() {
+        assert_eq!(inset(size(100, 40), 50), size(80, 20));
+        assert_eq!(inset(size(800, 600), u32::MAX), size(500, 300));
+    }
+
+    #[test]
+    fn inset_leaves_at_least_a_pixel() {
+        assert_eq!(inset(size(0, 0), 5), size(1, 1));
+        assert_eq!(inset(size(1, 1), 0), size(1, 1));
+        assert_eq!(inset(size(3, 1000), 10), size(3, 1000));
+    }
+
+    #[test]
+    fn window_size_fits_the_zoomed_image_and_padding() {
+        let max_size = size(1920, 1080);
+        assert_eq!(window_size((400, 300), 1.0, max_size, 0), size(400, 300));
+        assert_eq!(window_size((400, 300), 2.0, max_size, 0), size(800, 600));
+        assert_eq!(window_size((400, 300), 1.0, max_size, 10), size(420, 320));
+    }
+
+    #[test]
+    fn window_size_rounds_up() {
+        assert_eq!(window_size((3, 3), 0.5, size(100, 100), 0), size(2, 2));
+    }
+
+    #[test]
+    fn window_size_is_limited_to_the_maximum() {
+        let max_size = size(1920, 1080);
+        assert_eq!(window_size((4000, 100), 1.0, max_size, 0), size(1920, 100));
+        assert_eq!(window_size((1, 100_000), 0.1, max_size, 0), size(1, 1080));
+    }
+
+    #[test]
+    fn window_size_survives_huge_paddings_and_zooms() {
+        let max_size = size(1920, 1080);
+        assert_eq!(window_size((400, 300), 1.0, max_size, u32::MAX), max_size);
+        assert_eq!(
+            window_size((u32::MAX, 1), MAX_ZOOM, max_size, 0),
+            size(1920, 20)
+        );
+    }
+
+    #[test]
+    fn window_size_is_never_empty() {
+        assert_eq!(window_size((0, 0), 1.0, size(100, 100), 0), size(1, 1));
+        assert_eq!(window_size((0, 10), 0.0, size(100, 100), 0), size(1, 1));
+    }
+
+    #[test]
+    fn composite_keeps_opaque_pixels() {
+        let out = composite(&[10, 20, 30, 0xff], 1, 1, |_, _| [0xff; 3]);
+        assert_eq!(out, [10, 20, 30, 0xff]);
+    }
+
+    #[test]
+    fn composite_shows_the_backdrop_through_transparency() {
+        let out = composite(&[10, 20, 30, 0], 1, 1, |_, _| [1, 2, 3]);
+        assert_eq!(out, [1, 2, 3, 0xff]);
+    }
+
+    #[test]
+    fn composite_blends_translucent_pixels() {
+        let out = composite(&[0xff, 0, 0, 128], 1, 1, |_, _| [0, 0, 0xff]);
+        assert_eq!(out, [128, 0, 127, 0xff]);
+    }
+
+    #[test]
+    fn composite_asks_for_the_backdrop_of_every_position() {
+        let out = composite(&[0; 3 * 2 * 4], 3, 2, |x, y| [x as u8, y as u8, 7]);
+        assert_eq!(pixel_at(&out, 3, 0, 0), [0, 0, 7, 0xff]);
+        assert_eq!(pixel_at(&out, 3, 2, 0), [2, 0, 7, 0xff]);
+        assert_eq!(pixel_at(&out, 3, 1, 1), [1, 1, 7, 0xff]);
+        assert_eq!(pixel_at(&out, 3, 2, 1), [2, 1, 7, 0xff]);
+    }
+
+    #[test]
+    fn composite_of_nothing_is_nothing() {
+        assert!(composite(&[], 0, 0, |_, _| [0; 3]).is_empty());
+        assert!(composite(&[], 0, 5, |_, _| [0; 3]).is_empty());
+    }
+
+    #[test]
+    fn checkerboard_alternates_squares() {
+        let out = composite_over_checkerboard(&[0; 16 * 16 * 4], 16, 16);
+        let light = [CHECKER_LIGHT[0], CHECKER_LIGHT[1], CHECKER_LIGHT[2], 0xff];
+        let dark = [CHECKER_DARK[0], CHECKER_DARK[1], CHECKER_DARK[2], 0xff];
+        assert_eq!(pixel_at(&out, 16, 0, 0), light);
+        assert_eq!(pixel_at(&out, 16, 7, 7), light);
+        assert_eq!(pixel_at(&out, 16, 8, 0), dark);
+        assert_eq!(pixel_at(&out, 16, 0, 8), dark);
+        assert_eq!(pixel_at(&out, 16, 8, 8), light);
+        assert_eq!(pixel_at(&out, 16, 15, 15), light);
+    }
+
+    #[test]
+    fn checkerboard_works_for_single_pixel_strips() {
+        let dark = [CHECKER_DARK[0], CHECKER_DARK[1], CHECKER_DARK[2], 0xff];
+        let out = composite_over_checkerboard(&[0; 100 * 4], 1, 100);
+        assert_eq!(pixel_at(&out, 1, 0, 8), dark);
+        let out = composite_over_checkerboard(&[0; 100 * 4], 100, 1);
+        assert_eq!(pixel_at(&out, 100, 8, 0), dark);
+    }
+
+    #[test]
+    fn checkerboard_hides_behind_opaque_pixels() {
+        let image = solid(16, 16, WHITE);
+        let out = composite_over_checkerboard(image.as_raw(), 16, 16);
+        assert_eq!(&out, image.as_raw());
+    }
+
+    #[test]
+    fn rotate_turns_clockwise() {
+        let image = numbered(3, 2);
+        let quarter = rotate(&image, 1);
+        assert_eq!(quarter.dimensions(), (2, 3));
+        assert_eq!(quarter.get_pixel(0, 0).0, [0, 1, 0, 0xff]);
+        assert_eq!(quarter.get_pixel(1, 0).0, [0, 0, 0, 0xff]);
+        let half = rotate(&image, 2);
+        assert_eq!(half.dimensions(), (3, 2));
+        assert_eq!(half.get_pixel(0, 0).0, [2, 1, 0, 0xff]);
+        let three_quarters = rotate(&image, 3);
+        assert_eq!(three_quarters.dimensions(), (2, 3));
+        assert_eq!(three_quarters.get_pixel(0, 0).0, [2, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn rotate_wraps_around_full_turns() {
+        let image = numbered(3, 2);
+        assert_eq!(rotate(&image, 0), image);
+        assert_eq!(rotate(&image, 4), image);
+        assert_eq!(rotate(&image, 5), rotate(&image, 1));
+    }
+
+    #[test]
+    fn apply_does_nothing_by_default() {
+        let image = numbered(4, 3);
+        assert_eq!(apply(&image, Transform::default()), image);
+    }
+
+    #[test]
+    fn apply_crops_first() {
+        let transform = Transform {
+            crop: Some(Crop {
+                x: 1,
+                y: 1,
+                width: 2,
+                height: 2,
+            }),
+            rotation: 1,
+            ..Transform::default()
+        };
+        let image = apply(&numbered(4, 3), transform);
+        assert_eq!(image.dimensions(), (2, 2));
+        assert_eq!(image.get_pixel(0, 0).0, [1, 2, 0, 0xff]);
+    }
+
+    #[test]
+    fn apply_flips_after_rotating() {
+        let transform = Transform {
+            rotation: 1,
+            flip_horizontal: true,
+            ..Transform::default()
+        };
+        let image = apply(&numbered(3, 2), transform);
+        assert_eq!(image.dimensions(), (2, 3));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 0xff]);
+        assert_eq!(image.get_pixel(1, 2).0, [2, 1, 0, 0xff]);
+    }
+
+    #[test]
+    fn apply_turns_extreme_strips() {
+        let transform = Transform {
+            rotation: 1,
+            ..Transform::default()
+        };
+        assert_eq!(apply(&numbered(1, 1000), transform).dimensions(), (1000, 1));
+        assert_eq!(apply(&RgbaImage::new(0, 0), transform).dimensions(), (0, 0));
+        assert_eq!(apply(&RgbaImage::new(0, 7), transform).dimensions(), (7, 0));
+    }
+
+    /// Whatever the geometry does, every displayed pixel maps back to the frame pixel it shows.
+    #[test]
+    fn frame_point_finds_where_displayed_pixels_came_from() {
+        let frame = numbered(5, 3);
+        let crops = [
+            None,
+            Some(Crop {
+                x: 1,
+                y: 1,
+                width: 3,
+                height: 2,
+            }),
+        ];
+        for rotation in 0..4 {
+            for flip_horizontal in [false, true] {
+                for flip_vertical in [false, true] {
+                    for crop in crops {
+                        let transform = Transform {
+                            rotation,
+                            flip_horizontal,
+                            flip_vertical,
+                            crop,
+                            ..Transform::default()
+                        };
+                        let image = apply(&frame, transform);
+                        for (x, y, pixel) in image.enumerate_pixels() {
+                            let center = (x as f32 + 0.5, y as f32 + 0.5);
+                            let (fx, fy) = transform.frame_point(center, frame.dimensions());
+                            assert_eq!(
+                                frame.get_pixel(fx as u32, fy as u32),
+                                pixel,
+                                "{:?} at {},{}",
+                                transform,
+                                x,
+                                y
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_geometry_changes_move_pixels() {
+        assert!(Transform::default().keeps_geometry());
+        let colors = Transform {
+            brightness: 0.5,
+            invert: true,
+            grayscale: true,
+            ..Transform::default()
+        };
+        assert!(colors.keeps_geometry());
+        let flipped = Transform {
+            flip_vertical: true,
+            ..Transform::default()
+        };
+        assert!(!flipped.keeps_geometry());
+        let cropped = Transform {
+            crop: Some(Crop {
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 1,
+            }),
+            ..Transform::default()
+        };
+        assert!(!cropped.keeps_geometry());
+    }
+
+    #[test]
+    fn color_table_leaves_colors_alone_by_default() {
+        let table = Transform::default().color_table();
+        assert!(table
+            .iter()
+            .enumerate()
+            .all(|(value, &entry)| entry as usize == value));
+        assert!(!Transform::default().adjusts_colors());
+    }
+
+    #[test]
+    fn crop_to_covers_every_pixel_the_selection_touches() {
+        let crop = Transform::default().crop_to(((6.1, 4.0), (2.5, 1.2)), (10, 8));
+        assert_eq!(
+            crop,
+            Some(Crop {
+                x: 2,
+                y: 1,
+                width: 5,
+                height: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn crop_to_narrows_down_the_current_crop() {
+        let transform = Transform {
+            crop: Some(Crop {
+                x: 2,
+                y: 2,
+                width: 6,
+                height: 4,
+            }),
+            ..Transform::default()
+        };
+        let crop = transform.crop_to(((1.0, 1.0), (3.0, 2.0)), (10, 8));
+        assert_eq!(
+            crop,
+            Some(Crop {
+                x: 3,
+                y: 3,
+                width: 2,
+                height: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn crop_to_undoes_the_rotation() {
+        let transform = Transform {
+            rotation: 1,
+            ..Transform::default()
+        };
+        let frame = numbered(4, 2);
+        let crop = transform.crop_to(((0.0, 0.0), (1.0, 1.0)), frame.dimensions());
+        let cropped = apply(&frame, Transform { crop, ..transform });
+        assert_eq!(cropped.dimensions(), (1, 1));
+        assert_eq!(
+            cropped.get_pixel(0, 0),
+            apply(&frame, transform).get_pixel(0, 0)
+        );
+    }
+
+    #[test]
+    fn crop_to_stays_inside_the_frame() {
+        let transform = Transform::default();
+        assert_eq!(
+            transform.crop_to(((-5.0, -5.0), (3.0, 3.0)), (10, 8)),
+            Some(Crop {
+                x: 0,
+                y: 0,
+                width: 3,
+                height: 3,
+            })
+        );
+        assert_eq!(
+            transform.crop_to(((20.0, 20.0), (30.0, 30.0)), (10, 8)),
+            None
+        );
+        assert_eq!(transform.crop_to(((2.0, 2.0), (2.0, 5.0)), (10, 8)), None);
+    }
+
+    #[test]
+    fn crops_scale_with_their_frame() {
+        let crop = Crop {
+            x: 1,
+            y: 2,
+            width: 3,
+            height: 4,
+        };
+        assert_eq!(
+            crop.scaled(2.0),
+            Crop {
+                x: 2,
+                y: 4,
+                width: 6,
+                height: 8,
+            }
+        );
+        // Never down to nothing.
+        assert_eq!(
+            crop.scaled(0.1),
+            Crop {
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn brightness_saturates() {
+        let image = solid(1, 1, [10, 20, 30, 40]);
+        let brighter = Transform {
+            brightness: 1.0,
+            ..Transform::default()
+        };
+        assert_eq!(
+            apply(&image, brighter).get_pixel(0, 0).0,
+            [0xff, 0xff, 0xff, 40]
+        );
+        let darker = Transform {
+            brightness: -1.0,
+            ..Transform::default()
+        };
+        assert_eq!(apply(&image, darker).get_pixel(0, 0).0, [0, 0, 0, 40]);
+    }
+
+    #[test]
+    fn contrast_pushes_colors_from_mid_gray() {
+        let flat = Transform {
+            contrast: 0.0,
+            ..Transform::default()
+        };
+        let image = solid(1, 1, [0, 0xff, 77, 0xff]);
+        assert_eq!(apply(&image, flat).get_pixel(0, 0).0, [128, 128, 128, 0xff]);
+        let strong = Transform {
+            contrast: 2.0,
+            ..Transform::default()
+        };
+        let image = solid(1, 1, [0, 0xff, 32, 0xff]);
+        assert_eq!(apply(&image, strong).get_pixel(0, 0).0, [0, 0xff, 0, 0xff]);
+    }
+
+    #[test]
+    fn invert_keeps_alpha() {
+        let transform = Transform {
+            invert: true,
+            ..Transform::default()
+        };
+        let image = solid(1, 1, [10, 20, 30, 40]);
+        assert_eq!(
+            apply(&image, transform).get_pixel(0, 0).0,
+            [245, 235, 225, 40]
+        );
+    }
+
+    #[test]
+    fn invert_comes_after_brightness() {
+        let transform = Transform {
+            brightness: 1.0,
+            invert: true,
+            ..Transform::default()
+        };
+        let image = solid(1, 1, [10, 20, 30, 40]);
+        assert_eq!(apply(&image, transform).get_pixel(0, 0).0, [0, 0, 0, 40]);
+    }
+
+    #[test]
+    fn grayscale_weighs_channels_by_luminance() {
+        let transform = Transform {
+            grayscale: true,
+            ..Transform::default()
+        };
+        let gray = |pixel: [u8; 4]| apply(&solid(1, 1, pixel), transform).get_pixel(0, 0).0;
+        assert_eq!(gray([0xff, 0, 0, 0xff]), [54, 54, 54, 0xff]);
+        assert_eq!(gray([0, 0xff, 0, 0xff]), [182, 182, 182, 0xff]);
+        assert_eq!(gray([0, 0, 0xff, 10]), [18, 18, 18, 10]);
+        assert_eq!(gray(WHITE), WHITE);
+    }
+
+    #[test]
+    fn grayscale_comes_after_the_color_adjustments() {
+        let transform = Transform {
+            invert: true,
+            grayscale: true,
+            ..Transform::default()
+        };
+        let image = solid(1, 1, WHITE);
+        assert_eq!(apply(&image, transform).get_pixel(0, 0).0, [0, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn prepare_flattens_onto_the_backdrop() {
+        let image = solid(2, 2, CLEAR);
+        let prepared = prepare(&image, Transform::default(), Backdrop::Solid(BLUE));
+        assert!(prepared.pixels().all(|pixel| pixel.0 == BLUE.to_rgba()));
+        let prepared = prepare(&image, Transform::default(), Backdrop::Checkerboard);
+        assert!(prepared.pixels().all(|pixel| pixel.0[3] == 0xff));
+    }
+
+    #[test]
+    fn prepare_lays_the_checkerboard_over_the_displayed_image() {
+        let transform = Transform {
+            rotation: 1,
+            ..Transform::default()
+        };
+        let prepared = prepare(&solid(16, 8, CLEAR), transform, Backdrop::Checkerboard);
+        assert_eq!(prepared.dimensions(), (8, 16));
+        assert_eq!(prepared.get_pixel(0, 8).0[..3], CHECKER_DARK);
+        assert_eq!(prepared.get_pixel(0, 0).0[..3], CHECKER_LIGHT);
+    }
+
+    #[test]
+    fn zoom_at_keeps_the_anchor_in_place() {
+        let mut view = View::new(1.0, 0, 1.0);
+        view.offset = (10.0, -5.0);
+        let anchor = (50.0, 20.0);
+        let under = |view: &View| {
+            (
+                (anchor.0 - view.offset.0) / view.zoom,
+                (anchor.1 - view.offset.1) / view.zoom,
+            )
+        };
+        let before = under(&view);
+        view.zoom_at(2.0, anchor);
+        assert_eq!(view.zoom, 2.0);
+        assert_eq!(under(&view), before);
+        assert!(!view.fitted);
+    }
+
+    #[test]
+    fn zoom_stays_in_range() {
+        let mut view = View::new(1.0, 0, 1.0);
+        view.zoom_by(1000.0);
+        assert_eq!(view.zoom, MAX_ZOOM);
+        view.zoom_by(1e-6);
+        assert_eq!(view.zoom, MIN_ZOOM);
+    }
+
+    #[test]
+    fn perceived_zoom_goes_by_logical_pixels() {
+        let view = View::new(3.0, 0, 2.0);
+        assert_eq!(view.perceived_zoom(), 1.5);
+    }
+
+    #[test]
+    fn refit_undoes_zooming_and_panning() {
+        let mut view = View {
+            stretch: true,
+            ..View::new(1.0, 0, 1.0)
+        };
+        view.zoom_at(3.0, (20.0, 20.0));
+        view.refit(FitMode::Contain, size(400, 300), &numbered(800, 300));
+        assert_eq!(view.zoom, 0.5);
+        assert_eq!(view.offset, (0.0, 0.0));
+        assert!(view.fitted);
+        assert!(view.stretch);
+    }
+
+    #[test]
+    fn refit_to_the_width_starts_at_the_top() {
+        let mut view = View::new(1.0, 0, 1.0);
+        view.refit(FitMode::Width, size(200, 300), &numbered(100, 1000));
+        assert_eq!(view.zoom, 2.0);
+        // Pushed down as far as it goes, until the top edge meets the top of the window.
+        assert_eq!(view.offset, (0.0, 850.0));
+    }
+
+    #[test]
+    fn start_at_zooms_and_pans_from_the_middle() {
+        let mut view = View::new(1.0, 0, 2.0);
+        let image = numbered(1000, 1000);
+        view.start_at(Some(1.5), Some((10.0, -10.0)), size(400, 300), &image);
+        assert_eq!(view.zoom, 3.0);
+        assert_eq!(view.offset, (10.0, -10.0));
+        assert!(!view.fitted);
+    }
+
+    #[test]
+    fn start_at_brings_zoom_and_pan_into_range() {
+        let mut view = View::new(1.0, 0, 1.0);
+        let image = numbered(1000, 1000);
+        view.start_at(Some(100.0), None, size(400, 300), &image);
+        assert_eq!(view.zoom, MAX_ZOOM);
+        let mut view = View::new(1.0, 0, 1.0);
+        view.start_at(None, Some((5000.0, 0.0)), size(400, 300), &image);
+        assert_eq!(view.zoom, 1.0);
+        assert_eq!(view.offset, (300.0, 0.0));
+        assert!(view.fitted);
+    }
+
+    #[test]
+    fn clamp_keeps_large_images_over_the_window() {
+        let mut view = View::new(1.0, 0, 1.0);
+        view.offset = (1000.0, -1000.0);
+        view.clamp(size(300, 300), &numbered(500, 400));
+        assert_eq!(view.offset, (100.0, -50.0));
+    }
+
+    #[test]
+    fn clamp_centers_small_images() {
+        let mut view = View::new(1.0, 0, 1.0);
+        view.offset = (30.0, -30.0);
+        view.clamp(size(300, 300), &numbered(100, 100));
+        assert_eq!(view.offset, (0.0, 0.0));
+    }
+
+    #[test]
+    fn clamp_stops_at_the_padding() {
+        let mut view = View::new(1.0, 50, 1.0);
+        view.offset = (1000.0, 1000.0);
+        view.clamp(size(300, 300), &numbered(300, 300));
+        assert_eq!(view.offset, (50.0, 50.0));
+    }
+
+    #[test]
+    fn fits_needs_both_sides_in_the_padded_area() {
+        let image = numbered(100, 400);
+        let view = View::new(1.0, 0, 1.0);
+        assert!(view.fits_horizontally(size(300, 300), &image));
+        assert!(!view.fits(size(300, 300), &image));
+        assert!(view.fits(size(300, 400), &image));
+        let padded = View::new(1.0, 20, 1.0);
+        assert!(!padded.fits(size(300, 400), &image));
+        let stretched = View {
+            stretch: true,
+            ..View::new(10.0, 0, 1.0)
+        };
+        assert!(stretched.fits(size(300, 300), &image));
+    }
+
+    #[test]
+    fn center_on_brings_the_point_to_the_middle() {
+        let image = numbered(400, 400);
+        let mut view = View::new(1.0, 0, 1.0);
+        view.center_on((150.0, 250.0), size(200, 200), &image);
+        assert_eq!(view.offset, (50.0, -50.0));
+        let placement = Placement::new(200, 200, &image, &view);
+        assert_eq!(
+            placement.image_point(PhysicalPosition::new(100.0, 100.0)),
+            (150.0, 250.0)
+        );
+    }
+
+    #[test]
+    fn center_on_stops_at_the_edges() {
+        let mut view = View::new(1.0, 0, 1.0);
+        view.center_on((0.0, 400.0), size(200, 200), &numbered(400, 400));
+        assert_eq!(view.offset, (100.0, -100.0));
+    }
+
+    #[test]
+    fn views_glide_from_where_they_were_shown() {
+        let mut view = View::new(1.0, 0, 1.0);
+        view.glide();
+        let start = view.glide.unwrap().start;
+        view.zoom_by(2.0);
+        view.pan(10.0, 0.0);
+
+        let shown = view.shown(start);
+        assert_eq!((shown.zoom, shown.offset), (1.0, (0.0, 0.0)));
+        // Eased out, so more than halfway there halfway through.
+        let halfway = view.shown(start + GLIDE_TIME / 2);
+        assert_close(halfway.zoom, 1.875);
+        assert_close(halfway.offset.0, 8.75);
+        let end = start + GLIDE_TIME;
+        let shown = view.shown(end);
+        assert_eq!((shown.zoom, shown.offset), (2.0, (10.0, 0.0)));
+        assert!(shown.glide.is_none());
+
+        view.settle(start + GLIDE_TIME / 2);
+        assert!(view.glide.is_some());
+        view.settle(end);
+        assert!(view.glide.is_none());
+        assert_eq!(view.shown(start).zoom, 2.0);
+    }
+
+    #[test]
+    fn placement_centers_the_image() {
+        let placement = Placement::new(400, 300, &numbered(100, 50), &View::new(1.0, 0, 1.0));
+        assert_eq!((placement.left, placement.top), (150.0, 125.0));
+        assert_eq!((placement.width, placement.height), (100.0, 50.0));
+        assert_eq!(placement.zoom, (1.0, 1.0));
+    }
+
+    #[test]
+    fn placement_scales_by_the_zoom() {
+        let placement = Placement::new(400, 300, &numbered(100, 50), &View::new(2.0, 0, 1.0));
+        assert_eq!((placement.left, placement.top), (100.0, 100.0));
+        assert_eq!((placement.width, placement.height), (200.0, 100.0));
+        assert_eq!(placement.zoom, (2.0, 2.0));
+    }
+
+    #[test]
+    fn placement_moves_by_the_offset() {
+        let mut view = View::new(1.0, 0, 1.0);
+        view.offset = (10.0, -20.0);
+        let placement = Placement::new(400, 300, &numbered(100, 50), &view);
+        assert_eq!((placement.left, placement.top), (160.0, 105.0));
+    }
+
+    #[test]
+    fn placement_may_overflow_the_window() {
+        let placement = Placement::new(300, 300, &numbered(1, 100), &View::new(10.0, 0, 1.0));
+        assert_eq!(placement.top, -350.0);
+        assert_eq!(placement.height, 1000.0);
+    }
+
+    #[test]
+    fn stretched_placement_fills_the_padded_area() {
+        let mut view = View {
+            stretch: true,
+            ..View::new(1.0, 10, 1.0)
+        };
+        view.offset = (30.0, 30.0);
+        let placement = Placement::new(400, 300, &numbered(100, 50), &view);
+        assert_eq!((placement.left, placement.top), (10.0, 10.0));
+        assert_eq!((placement.width, placement.height), (380.0, 280.0));
+        assert_close(placement.zoom.0, 3.8);
+        assert_close(placement.zoom.1, 5.6);
+    }
+
+    #[test]
+    fn window_center_is_the_image_center() {
+        let placement = Placement::new(400, 300, &numbered(100, 50), &View::new(3.0, 0, 1.0));
+        assert_eq!(
+            placement.image_point(PhysicalPosition::new(200.0, 150.0)),
+            (50.0, 25.0)
+        );
+    }
+
+    #[test]
+    fn image_and_window_points_round_trip() {
+        let mut view = View {
+            stretch: true,
+            ..View::new(1.0, 7, 1.0)
+        };
+        for stretch in [false, true] {
+            view.stretch = stretch;
+            view.zoom = 2.5;
+            let placement = Placement::new(400, 300, &numbered(100, 50), &view);
+            for point in [(0.0, 0.0), (10.0, 20.0), (100.0, 50.0), (-4.0, 64.0)] {
+                let (x, y) = placement.window_point(point.0, point.1);
+                let back = placement.image_point(PhysicalPosition::new(x as f64, y as f64));
+                // Window points are rounded to whole pixels.
+                assert!((back.0 - point.0).abs() <= 0.5 / placement.zoom.0 + 1e-4);
+                assert!((back.1 - point.1).abs() <= 0.5 / placement.zoom.1 + 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn covered_goes_by_pixel_centers() {
+        assert_eq!(Placement::covered(10.0, 5.0, 100), 10..15);
+        assert_eq!(Placement::covered(10.6, 1.0, 100), 11..12);
+        assert_eq!(Placement::covered(10.4, 1.0, 100), 10..11);
+        assert!(Placement::covered(10.1, 0.3, 100).is_empty());
+    }
+
+    #[test]
+    fn covered_is_clipped_to_the_window() {
+        assert_eq!(Placement::covered(-50.0, 100.0, 30), 0..30);
+        assert_eq!(Placement::covered(20.0, 100.0, 30), 20..30);
+        assert!(Placement::covered(-50.0, 10.0, 30).is_empty());
+        assert!(Placement::covered(50.0, 10.0, 30).is_empty());
+        assert!(Placement::covered(0.0, 10.0, 0).is_empty());
+    }
+
+    #[test]
+    fn draw_paints_the_background_around_the_image() {
+        let image = solid(4, 2, WHITE);
+        let frame = drawn(8, 6, &image, &View::new(1.0, 0, 1.0));
+        assert_eq!(pixel_at(&frame, 8, 1, 1), BLUE.to_rgba());
+        assert_eq!(pixel_at(&frame, 8, 2, 2), WHITE);
+        assert_eq!(pixel_at(&frame, 8, 5, 3), WHITE);
+        assert_eq!(pixel_at(&frame, 8, 6, 3), BLUE.to_rgba());
+        assert_eq!(pixel_at(&frame, 8, 3, 4), BLUE.to_rgba());
+    }
+
+    #[test]
+    fn draw_keeps_the_padding_clear() {
+        let image = solid(20, 20, WHITE);
+        let frame = drawn(10, 10, &image, &View::new(1.0, 2, 1.0));
+        assert_eq!(pixel_at(&frame, 10, 1, 5), BLUE.to_rgba());
+        assert_eq!(pixel_at(&frame, 10, 2, 5), WHITE);
+        assert_eq!(pixel_at(&frame, 10, 7, 7), WHITE);
+        assert_eq!(pixel_at(&frame, 10, 8, 7), BLUE.to_rgba());
+    }
+
+    #[test]
+    fn draw_blows_up_pixels_into_squares() {
+        let image = numbered(2, 2);
+        let frame = drawn(4, 4, &image, &View::new(2.0, 0, 1.0));
+        assert_eq!(pixel_at(&frame, 4, 0, 0), [0, 0, 0, 0xff]);
+        assert_eq!(pixel_at(&frame, 4, 1, 1), [0, 0, 0, 0xff]);
+        assert_eq!(pixel_at(&frame, 4, 2, 1), [1, 0, 0, 0xff]);
+        assert_eq!(pixel_at(&frame, 4, 1, 2), [0, 1, 0, 0xff]);
+        assert_eq!(pixel_at(&frame, 4, 3, 3), [1, 1, 0, 0xff]);
+    }
+
+    #[test]
+    fn draw_stretches_over_the_whole_area() {
+        let view = View {
+            stretch: true,
+            ..View::new(1.0, 0, 1.0)
+        };
+        let frame = drawn(5, 3, &solid(1, 1, WHITE), &view);
+        assert!(frame.chunks_exact(4).all(|pixel| pixel == WHITE));
+    }
+
+    #[test]
+    fn draw_handles_tiny_windows_and_images() {
+        let frame = drawn(1, 1, &numbered(1000, 1), &View::new(MIN_ZOOM, 0, 1.0));
+        assert_eq!(frame.len(), 4);
+        let frame = drawn(1, 1, &solid(1, 1, WHITE), &View::new(MAX_ZOOM, 0, 1.0));
+        assert_eq!(pixel_at(&frame, 1, 0, 0), WHITE);
+    }
+
+    #[test]
+    fn draw_columns_leaves_other_columns_alone() {
+        let mut frame = vec![9; 4 * 2 * 4];
+        let image = solid(4, 2, WHITE);
+        let view = View::new(1.0, 0, 1.0);
+        draw_columns(
+            &mut frame,
+            (4, 2),
+            0..2,
+            &image,
+            &view,
+            BLUE,
+            Filter::Nearest,
+        );
+        assert_eq!(pixel_at(&frame, 4, 1, 1), WHITE);
+        assert_eq!(pixel_at(&frame, 4, 2, 1), [9; 4]);
+    }
+
+    #[test]
+    fn draw_columns_clips_columns_past_the_window() {
+        let mut frame = vec![0; 4 * 2 * 4];
+        let image = solid(4, 2, WHITE);
+        let view = View::new(1.0, 0, 1.0);
+        draw_columns(
+            &mut frame,
+            (4, 2),
+            2..100,
+            &image,
+            &view,
+            BLUE,
+            Filter::Nearest,
+        );
+        assert_eq!(pixel_at(&frame, 4, 3, 1), WHITE);
+        assert_eq!(pixel_at(&frame, 4, 1, 1), [0; 4]);
+    }
+
+    #[test]
+    fn pixel_grid_lines_start_every_pixel() {
+        let image = solid(2, 2, WHITE);
+        let mut frame = drawn(16, 16, &image, &View::new(8.0, 0, 1.0));
+        draw_pixel_grid(&mut frame, (16, 16), 0..16, &image, &View::new(8.0, 0, 1.0));
+        let line = pixel_at(&frame, 16, 8, 3);
+        assert!(line[0] < 0xff && line[3] == 0xff);
+        assert_eq!(pixel_at(&frame, 16, 3, 8), line);
+        // Lines only go between pixels, not around the image.
+        assert_eq!(pixel_at(&frame, 16, 0, 3), WHITE);
+        assert_eq!(pixel_at(&frame, 16, 7, 3), WHITE);
+        assert_eq!(pixel_at(&frame, 16, 3, 0), WHITE);
+        // Crossings are blended once.
+        assert_eq!(pixel_at(&frame, 16, 8, 8), line);
+    }
+
+    #[test]
+    fn pixel_grid_needs_a_high_zoom() {
+        let image = solid(4, 4, WHITE);
+        let view = View::new(PIXEL_GRID_ZOOM - 1.0, 0, 1.0);
+        let mut frame = drawn(28, 28, &image, &view);
+        let before = frame.clone();
+        draw_pixel_grid(&mut frame, (28, 28), 0..28, &image, &view);
+        assert_eq!(frame, before);
+    }
+
+    #[test]
+    fn pixel_grid_stays_in_its_columns() {
+        let image = solid(2, 2, WHITE);
+        let view = View::new(8.0, 0, 1.0);
+        let mut frame = drawn(16, 16, &image, &view);
+        draw_pixel_grid(&mut frame, (16, 16), 0..8, &image, &view);
+        assert_eq!(pixel_at(&frame, 16, 8, 3), WHITE);
+        assert_eq!(pixel_at(&frame, 16, 10, 8), WHITE);
+        assert_ne!(pixel_at(&frame, 16, 3, 8), WHITE);
+    }
+
+    #[test]
+    fn linear_sampling_is_exact_at_pixel_centers() {
+        let image = numbered(3, 3);
+        assert_eq!(sample_linear(&image, 1.5, 1.5), [1, 1, 0, 0xff]);
+        assert_eq!(sample_linear(&image, 2.5, 0.5), [2, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn linear_sampling_blends_between_centers() {
+        let mut image = solid(2, 1, [0, 0, 0, 0xff]);
+        image.put_pixel(1, 0, Rgba([100, 0, 0, 0xff]));
+        assert_eq!(sample_linear(&image, 1.0, 0.5), [50, 0, 0, 0xff]);
+        assert_eq!(sample_linear(&image, 0.75, 0.5), [25, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn linear_sampling_stretches_the_edges() {
+        let image = numbered(3, 3);
+        assert_eq!(sample_linear(&image, -5.0, -5.0), [0, 0, 0, 0xff]);
+        assert_eq!(sample_linear(&image, 100.0, 100.0), [2, 2, 0, 0xff]);
+        assert_eq!(sample_linear(&solid(1, 1, WHITE), 0.9, 0.1), WHITE);
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    borrow::Cow,
+    fs,
+    path::Path,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use image::RgbaImage;
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{fontdb, Options, Tree},
+};
+
+use crate::{
+    archive,
+    picture::{Frame, Source},
+    Result, RvuError,
+};
+
+/// The longest side a vector image is rasterized at, however far it's zoomed in.
+const MAX_SIDE: u32 = 8192;
+
+/// Whether the image from `source` is an SVG, going by the extension of a file or an entry in
+/// an archive. Anything else is one unless it's in a raster format, as long as it's named like
+/// one or its document has an `<svg` root.
+pub fn is_svg(source: &Source) -> bool {
+    match source {
+        Source::File(path) => has_svg_extension(path),
+        Source::Memory { name, bytes } => {
+            image::guess_format(bytes).is_err()
+                && (has_svg_extension(Path::new(name)) || has_svg_root(bytes))
+        }
+        Source::Archived { entry, .. } => has_svg_extension(Path::new(entry)),
+    }
+}
+
+/// Whether the document in `bytes` starts with an `<svg` element, once past a byte order mark,
+/// the XML declaration, comments, processing instructions and a doctype.
+fn has_svg_root(bytes: &[u8]) -> bool {
+    let mut rest = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    loop {
+        let start = rest
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        rest = &rest[start..];
+        let end: &[u8] = if rest.starts_with(b"<!--") {
+            b"-->"
+        } else if rest.starts_with(b"<?") {
+            b"?>"
+        } else if rest.starts_with(b"<!") {
+            // A doctype may declare entities in brackets, which have `>` in them.
+            let bracket = rest.iter().position(|&byte| byte == b'[');
+            let close = rest.iter().position(|&byte| byte == b'>');
+            match (bracket, close) {
+                (Some(bracket), Some(close)) if bracket < close => b"]>",
+                _ => b">",
+            }
+        } else {
+            return rest.starts_with(b"<svg")
+                && rest
+                    .get(4)
+                    .is_some_and(|&byte| byte.is_ascii_whitespace() || b"/>".contains(&byte));
+        };
+        match rest.windows(end.len()).position(|window| window == end) {
+            Some(at) => rest = &rest[at + end.len()..],
+            None => return false,
+        }
+    }
+}
+
+/// Whether `path` is named like an SVG, compressed or not.
+pub fn has_svg_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("svg") || extension.eq_ignore_ascii_case("svgz")
+        })
+}
+
+/// Rasterizes the SVG from `source` at `scale` times the size it declares, as a single frame.
+pub fn load(source: &Source, scale: f32) -> Result<Vec<Frame>> {
+    let tree = parse(source)?;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(scale)
+        .ok_or(RvuError::EmptyImage)?;
+    let mut pixmap = Pixmap::new(size.width(), size.height()).ok_or(RvuError::EmptyImage)?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // The pixmap is premultiplied, the frames aren't.
+    let mut buffer = RgbaImage::new(size.width(), size.height());
+    for (pixel, color) in buffer.pixels_mut().zip(pixmap.pixels()) {
+        let color = color.demultiply();
+        pixel.0 = [color.red(), color.green(), color.blue(), color.alpha()];
+    }
+    Ok(vec![Frame {
+        buffer,
+        delay: Duration::ZERO,
+    }])
+}
+
+/// The size the SVG from `source` declares, in pixels.
+pub fn size(source: &Source) -> Result<(u32, u32)> {
+    let size = parse(source)?.size().to_int_size();
+    Ok((size.width(), size.height()))
+}
+
+/// The scale to rasterize an SVG declared `size` pixels big at, to look sharp at `zoom`: the
+/// next power of two, so that zooming doesn't rasterize it all over again at every step.
+pub fn raster_scale(zoom: f32, size: (u32, u32)) -> f32 {
+    let largest = MAX_SIDE as f32 / size.0.max(size.1).max(1) as f32;
+    let scale = zoom.max(1.0).log2().ceil().exp2();
+    scale.min(largest).max(1.0)
+}
+
+fn parse(source: &Source) -> Result<Tree> {
+    let mut options = Options {
+        fontdb: system_fonts(),
+        ..Options::default()
+    };
+    let data = match source {
+        Source::File(path) => {
+            // Images it links to are found next to it.
+            options.resources_dir = path.parent().map(Path::to_path_buf);
+            Cow::Owned(fs::read(path)?)
+        }
+        Source::Memory { bytes, .. } => Cow::Borrowed(&bytes[..]),
+        Source::Archived { archive, entry } => Cow::Owned(archive::read(archive, entry)?),
+    };
+    Ok(Tree::from_data(&data, &options)?)
+}
+
+/// The fonts installed on the system, for text in SVGs. Looked up once, since that's slow.
+fn system_fonts() -> Arc<fontdb::Database> {
+    static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut fonts = fontdb::Database::new();
+            fonts.load_system_fonts();
+            Arc::new(fonts)
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_memory(name: &str, bytes: &[u8]) -> Source {
+        Source::Memory {
+            name: name.to_string(),
+            bytes: bytes.into(),
+        }
+    }
+
+    #[test]
+    fn svg_roots_are_found_past_the_prolog() {
+        assert!(has_svg_root(b"<svg xmlns='http://www.w3.org/2000/svg'/>"));
+        assert!(has_svg_root(b"<svg>"));
+        assert!(has_svg_root(
+            b"\xef\xbb\xbf<?xml version='1.0'?>\n<!-- made by hand -->\n\
+              <!DOCTYPE svg PUBLIC '-//W3C//DTD SVG 1.1//EN' 'svg11.dtd'>\n<svg width='1'/>"
+        ));
+        assert!(has_svg_root(
+            b"<!DOCTYPE svg [ <!ENTITY a '<b>'> ]><?pi x?><svg/>"
+        ));
+    }
+
+    #[test]
+    fn other_documents_have_no_svg_root() {
+        assert!(!has_svg_root(b""));
+        assert!(!has_svg_root(b"<html><svg></svg></html>"));
+        assert!(!has_svg_root(b"<svgfoo/>"));
+        assert!(!has_svg_root(b"<!-- <svg> never closed"));
+        assert!(!has_svg_root(b"some text mentioning <svg>"));
+    }
+
+    #[test]
+    fn raster_images_are_never_svgs() {
+        // A PNG with text that has an `<svg` in it, like XMP metadata might.
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend_from_slice(b"tEXt<svg xmlns='http://www.w3.org/2000/svg'/>");
+        assert!(!is_svg(&in_memory("(stdin)", &png)));
+        assert!(!is_svg(&in_memory("https://example.com/icon.svg", &png)));
+        assert!(is_svg(&in_memory("(stdin)", b"<svg/>")));
+    }
+
+    #[test]
+    fn svgs_are_known_by_name_too() {
+        // Such as a compressed one, which has no text to look at.
+        assert!(is_svg(&in_memory("book.cbz/icon.svgz", b"\x1f\x8b\x08")));
+        assert!(!is_svg(&in_memory("book.cbz/icon.txt", b"\x1f\x8b\x08")));
+        let archived = Source::Archived {
+            archive: "book.cbz".into(),
+            entry: "dir/icon.SVG".to_string(),
+        };
+        assert!(is_svg(&archived));
+    }
+}
@@ -0,0 +1,46 @@
+use std::{
+    io,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{Result, RvuError};
+
+/// Extensions of the video formats worth asking ffmpeg about.
+const EXTENSIONS: [&str; 11] = [
+    "mp4", "m4v", "mkv", "webm", "mov", "avi", "wmv", "flv", "mpg", "mpeg", "ogv",
+];
+
+/// Whether `path` is named like a video.
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            EXTENSIONS
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Has ffmpeg extract the first frame of the video at `path`, encoded as a PNG.
+pub fn first_frame(path: &Path) -> Result<Vec<u8>> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => RvuError::NoFfmpeg,
+            _ => err.into(),
+        })?;
+    if !output.status.success() || output.stdout.is_empty() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(RvuError::VideoError(if message.is_empty() {
+            "it has no video stream".to_string()
+        } else {
+            message
+        }));
+    }
+    Ok(output.stdout)
+}
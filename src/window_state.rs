@@ -0,0 +1,52 @@
+use std::{fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+/// Where the window was and how big it was when the viewer last closed, kept in `window.json`
+/// in the `img-vwr` state directory, e.g. `~/.local/state/img-vwr/window.json`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WindowState {
+    /// Outer position of the window.
+    pub x: i32,
+    pub y: i32,
+    /// Inner size of the window.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowState {
+    /// Where the state file lives. Platforms without a state directory keep it with the other
+    /// local data.
+    pub fn path() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("img-vwr").join("window.json"))
+    }
+
+    /// Reads the state file. A missing or broken one is no reason to fail, the window just
+    /// opens where it would have without it.
+    pub fn load() -> Option<Self> {
+        let text = fs::read_to_string(Self::path()?).ok()?;
+        let state: Self = serde_json::from_str(&text).ok()?;
+        (state.width > 0 && state.height > 0).then_some(state)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no state directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = serde_json::to_string(self).map_err(io::Error::from)?;
+        fs::write(path, text)
+    }
+
+    pub fn position(&self) -> PhysicalPosition<i32> {
+        PhysicalPosition::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.width, self.height)
+    }
+}